@@ -0,0 +1,270 @@
+use super::view::VideoInfo;
+use crate::config;
+use crate::store::{load_json, save_json, StoreError};
+use base64::{engine::general_purpose, Engine as _};
+use json::JsonValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// 按 BV 号缓存视频信息和封面，避免同一个视频反复请求接口和下载封面
+// 开启 persist_cache 时保存在 video_cache.json 中，封面用 base64 编码
+// {"infos": {"BV17x411w7KC": {"time": 0, "info": {...}}}, "covers": {"BV17x411w7KC": {"time": 0, "data": "..."}}}
+
+pub const VIDEO_CACHE_PATH: &str = "video_cache.json";
+
+#[derive(Debug, Default)]
+pub struct VideoCache {
+    path: Option<PathBuf>,
+    ttl: u64,
+    /// BV 号 -> (缓存的时间, 视频信息)
+    infos: HashMap<String, (u64, VideoInfo)>,
+    /// BV 号 -> (缓存的时间, 封面图片)
+    covers: HashMap<String, (u64, Vec<u8>)>,
+}
+
+fn invalid(msg: String) -> StoreError {
+    StoreError::Invalid(msg)
+}
+
+impl VideoCache {
+    pub fn new(ttl: Duration) -> VideoCache {
+        VideoCache {
+            ttl: ttl.as_secs(),
+            ..VideoCache::default()
+        }
+    }
+
+    pub fn from_json(value: &JsonValue, ttl: Duration) -> Result<VideoCache, StoreError> {
+        let mut cache = VideoCache::new(ttl);
+        for (bvid, entry) in value["infos"].entries() {
+            let info = VideoInfo::from_json(&entry["info"])
+                .map_err(|e| invalid(format!("info of {}: {}", bvid, e)))?;
+            let time = entry["time"].as_u64().unwrap_or_default();
+            cache.infos.insert(bvid.to_string(), (time, info));
+        }
+        for (bvid, entry) in value["covers"].entries() {
+            let data = entry["data"]
+                .as_str()
+                .and_then(|data| general_purpose::STANDARD.decode(data).ok())
+                .ok_or_else(|| invalid(format!("invalid cover of {}", bvid)))?;
+            let time = entry["time"].as_u64().unwrap_or_default();
+            cache.covers.insert(bvid.to_string(), (time, data));
+        }
+        Ok(cache)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut infos = JsonValue::new_object();
+        for (bvid, (time, info)) in &self.infos {
+            infos[bvid.as_str()] = json::object! {"time": *time, "info": info.to_json()};
+        }
+        let mut covers = JsonValue::new_object();
+        for (bvid, (time, data)) in &self.covers {
+            covers[bvid.as_str()] = json::object! {
+                "time": *time,
+                "data": general_purpose::STANDARD.encode(data),
+            };
+        }
+        json::object! {"infos": infos, "covers": covers}
+    }
+
+    /// 文件不存在时返回空的缓存，之后的修改会保存到 `path`
+    pub fn load<P: AsRef<Path>>(path: P, ttl: Duration) -> Result<VideoCache, StoreError> {
+        let mut cache = match load_json(&path)? {
+            Some(value) => VideoCache::from_json(&value, ttl)?,
+            None => VideoCache::new(ttl),
+        };
+        cache.path = Some(path.as_ref().to_path_buf());
+        Ok(cache)
+    }
+
+    fn save(&self) -> Result<(), StoreError> {
+        match &self.path {
+            Some(path) => Ok(save_json(path, &self.to_json())?),
+            None => Ok(()),
+        }
+    }
+
+    fn expired(&self, time: u64, now: u64) -> bool {
+        now.saturating_sub(time) >= self.ttl
+    }
+
+    /// 删掉过期的缓存，在写入的时候顺便调用
+    fn prune(&mut self, now: u64) {
+        let ttl = self.ttl;
+        let alive = |time: u64| now.saturating_sub(time) < ttl;
+        self.infos.retain(|_, (time, _)| alive(*time));
+        self.covers.retain(|_, (time, _)| alive(*time));
+    }
+
+    pub fn info(&self, bvid: &str, now: u64) -> Option<&VideoInfo> {
+        self.infos
+            .get(bvid)
+            .filter(|(time, _)| !self.expired(*time, now))
+            .map(|(_, info)| info)
+    }
+
+    pub fn cover(&self, bvid: &str, now: u64) -> Option<&[u8]> {
+        self.covers
+            .get(bvid)
+            .filter(|(time, _)| !self.expired(*time, now))
+            .map(|(_, data)| data.as_slice())
+    }
+
+    pub fn insert_info(&mut self, bvid: &str, info: VideoInfo, now: u64) -> Result<(), StoreError> {
+        self.prune(now);
+        self.infos.insert(bvid.to_string(), (now, info));
+        self.save()
+    }
+
+    pub fn insert_cover(&mut self, bvid: &str, data: Vec<u8>, now: u64) -> Result<(), StoreError> {
+        self.prune(now);
+        self.covers.insert(bvid.to_string(), (now, data));
+        self.save()
+    }
+}
+
+/// 记录每个群最近预览过的视频，同一个视频在冷却时间内不重复预览
+#[derive(Debug, Default)]
+pub struct Cooldown {
+    /// (群号, BV 号) -> 上次预览的时间
+    previews: HashMap<(i64, String), u64>,
+}
+
+impl Cooldown {
+    /// 可以预览时占住这个视频并返回 `true`，冷却中返回 `false`
+    ///
+    /// 在请求之前占住，同一个链接连发两次也只预览一次，没有发出去时用 `release` 释放
+    pub fn reserve(&mut self, group: i64, bvid: &str, now: u64, window: Duration) -> bool {
+        let window = window.as_secs();
+        self.previews
+            .retain(|_, time| now.saturating_sub(*time) < window);
+        let key = (group, bvid.to_string());
+        if self.previews.contains_key(&key) {
+            return false;
+        }
+        if window > 0 {
+            self.previews.insert(key, now);
+        }
+        true
+    }
+
+    /// 请求失败或者发送失败时释放，下次还可以重试
+    pub fn release(&mut self, group: i64, bvid: &str) {
+        self.previews.remove(&(group, bvid.to_string()));
+    }
+}
+
+static CACHE: OnceLock<Mutex<VideoCache>> = OnceLock::new();
+
+/// 按配置决定是否从 `VIDEO_CACHE_PATH` 读取，读取失败时记录日志并使用空的缓存
+pub fn global() -> &'static Mutex<VideoCache> {
+    CACHE.get_or_init(|| {
+        let video = &config::get().video;
+        if !video.persist_cache {
+            return Mutex::new(VideoCache::new(video.cache_ttl));
+        }
+        let cache = VideoCache::load(VIDEO_CACHE_PATH, video.cache_ttl).unwrap_or_else(|e| {
+            tracing::warn!("load {} error: {}", VIDEO_CACHE_PATH, e);
+            VideoCache {
+                path: Some(PathBuf::from(VIDEO_CACHE_PATH)),
+                ..VideoCache::new(video.cache_ttl)
+            }
+        });
+        Mutex::new(cache)
+    })
+}
+
+static COOLDOWN: OnceLock<Mutex<Cooldown>> = OnceLock::new();
+
+pub fn cooldown() -> &'static Mutex<Cooldown> {
+    COOLDOWN.get_or_init(|| Mutex::new(Cooldown::default()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info(title: &str) -> VideoInfo {
+        VideoInfo {
+            title: title.to_string(),
+            cover: String::from("http://i0.hdslb.com/cover.jpg"),
+            ..VideoInfo::default()
+        }
+    }
+
+    #[test]
+    fn ttl_test() {
+        let mut cache = VideoCache::new(Duration::from_secs(60));
+        assert!(cache.info("BV17x411w7KC", 0).is_none());
+        cache.insert_info("BV17x411w7KC", info("a"), 100).unwrap();
+        cache
+            .insert_cover("BV17x411w7KC", vec![1, 2, 3], 100)
+            .unwrap();
+        assert_eq!(cache.info("BV17x411w7KC", 159), Some(&info("a")));
+        assert_eq!(cache.cover("BV17x411w7KC", 159), Some(&[1u8, 2, 3][..]));
+        assert!(cache.info("BV17x411w7KC", 160).is_none());
+        assert!(cache.cover("BV17x411w7KC", 160).is_none());
+
+        // 写入时清掉过期的
+        cache.insert_info("BV1xx411c7mD", info("b"), 200).unwrap();
+        assert_eq!(cache.infos.len(), 1);
+        assert!(cache.covers.is_empty());
+    }
+
+    #[test]
+    fn persist_test() {
+        let path =
+            std::env::temp_dir().join(format!("qq-bot-video-cache-{}.json", std::process::id()));
+        let ttl = Duration::from_secs(60);
+        let mut cache = VideoCache::load(&path, ttl).unwrap();
+        cache.insert_info("BV17x411w7KC", info("a"), 100).unwrap();
+        cache
+            .insert_cover("BV17x411w7KC", vec![0, 255], 100)
+            .unwrap();
+
+        let cache = VideoCache::load(&path, ttl).unwrap();
+        assert_eq!(cache.info("BV17x411w7KC", 120), Some(&info("a")));
+        assert_eq!(cache.cover("BV17x411w7KC", 120), Some(&[0u8, 255][..]));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            VideoCache::from_json(
+                &json::parse(r#"{"covers": {"BV17x411w7KC": {"data": 1}}}"#).unwrap(),
+                ttl
+            ),
+            Err(StoreError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn cooldown_test() {
+        let window = Duration::from_secs(600);
+        let mut cooldown = Cooldown::default();
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 0, window));
+        assert!(!cooldown.reserve(1, "BV17x411w7KC", 599, window));
+        // 其它群和其它视频不受影响
+        assert!(cooldown.reserve(2, "BV17x411w7KC", 599, window));
+        assert!(cooldown.reserve(1, "BV1xx411c7mD", 599, window));
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 600, window));
+
+        let mut cooldown = Cooldown::default();
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 0, Duration::ZERO));
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 0, Duration::ZERO));
+    }
+
+    #[test]
+    fn cooldown_release_test() {
+        let window = Duration::from_secs(600);
+        let mut cooldown = Cooldown::default();
+        // 第一次请求还没完成时同一个链接又发了一次
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 0, window));
+        assert!(!cooldown.reserve(1, "BV17x411w7KC", 0, window));
+        // 请求失败释放之后马上还可以预览
+        cooldown.release(1, "BV17x411w7KC");
+        assert!(cooldown.reserve(1, "BV17x411w7KC", 1, window));
+        assert!(!cooldown.reserve(1, "BV17x411w7KC", 2, window));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::timeout::Timeouts;
+use json::JsonValue;
+use std::fmt;
+
+// 从消息中找出 B 站视频链接，支持 BV 号、av 号、分 P、短链接和分享卡片
+
+const TABLE: &[u8; 58] = b"FcwAPNKTMug3GV5Lj7EJnHpWsx4tb8haYeviqBz6rkCy12mUSDQX9RdoZf";
+const XOR_CODE: u64 = 23442827791579;
+const MASK_CODE: u64 = 2251799813685247;
+const MAX_AID: u64 = 1 << 51;
+const BVID_LENGTH: usize = 12;
+
+const VIDEO_PATH: &str = "bilibili.com/video/";
+const MIN_BARE_AV_DIGITS: usize = 5;
+/// 手机客户端分享出来的短链接域名
+const SHORT_HOSTS: [&str; 2] = ["b23.tv/", "bili2233.cn/"];
+
+/// av 号转 BV 号，参考 https://github.com/SocialSisterYi/bilibili-API-collect
+pub fn av_to_bv(aid: u64) -> Option<String> {
+    if aid == 0 || aid >= MAX_AID {
+        return None;
+    }
+    let mut bytes = *b"BV1000000000";
+    let mut tmp = (MAX_AID | aid) ^ XOR_CODE;
+    let mut index = BVID_LENGTH - 1;
+    while tmp > 0 {
+        bytes[index] = TABLE[(tmp % 58) as usize];
+        tmp /= 58;
+        index -= 1;
+    }
+    bytes.swap(3, 9);
+    bytes.swap(4, 7);
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+pub fn bv_to_av(bvid: &str) -> Option<u64> {
+    if !is_bvid(bvid) {
+        return None;
+    }
+    let mut bytes = bvid.as_bytes().to_vec();
+    bytes.swap(3, 9);
+    bytes.swap(4, 7);
+    let mut tmp: u64 = 0;
+    for c in &bytes[3..] {
+        let index = TABLE.iter().position(|t| t == c)? as u64;
+        tmp = tmp * 58 + index;
+    }
+    Some((tmp & MASK_CODE) ^ XOR_CODE)
+}
+
+fn is_bvid(bvid: &str) -> bool {
+    bvid.len() == BVID_LENGTH
+        && bvid.starts_with("BV1")
+        && bvid.bytes().skip(3).all(|c| TABLE.contains(&c))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoLink {
+    pub bvid: String,
+    /// 分 P，从 1 开始，链接里没有时为 `None`
+    pub part: Option<u32>,
+}
+
+impl fmt::Display for VideoLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "https://www.bilibili.com/video/{}", self.bvid)?;
+        match self.part {
+            Some(part) if part > 1 => write!(f, "?p={}", part),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `BV1xx411c7mD` 或者 `av170001`，返回 BV 号和用掉的长度
+/// 不在链接里的 av 号必须是小写的 `av` 加上至少 `MIN_BARE_AV_DIGITS` 位数字，
+/// 避免把 "AV1 编码" 这样的聊天内容当成视频
+fn parse_id(text: &str, bare: bool) -> Option<(String, usize)> {
+    let len = text
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(text.len());
+    let id = &text[..len];
+    if is_bvid(id) {
+        return Some((id.to_string(), len));
+    }
+    let digits = if bare {
+        id.strip_prefix("av")
+            .filter(|digits| digits.len() >= MIN_BARE_AV_DIGITS)?
+    } else {
+        id.get(..2)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("av"))
+            .map(|_| &id[2..])?
+    };
+    Some((av_to_bv(digits.parse().ok()?)?, len))
+}
+
+/// 链接里 `?` 之后的部分，到空白或者不会出现在链接里的字符为止
+fn query_len(text: &str) -> usize {
+    text.find(|c: char| !c.is_ascii_graphic() || matches!(c, '"' | '\'' | '<' | '>'))
+        .unwrap_or(text.len())
+}
+
+fn parse_part(query: &str) -> Option<u32> {
+    query
+        .trim_start_matches(['?', '/'])
+        .split(['&', '?', '#'])
+        .find_map(|param| param.strip_prefix("p="))
+        .and_then(|part| part.parse().ok())
+        .filter(|part| *part > 0)
+}
+
+/// 找出消息中所有的视频链接，包括 `http(s)://`、`www.`、`m.` 开头的链接和单独的 BV 号、av 号，
+/// 重复的只保留第一个
+pub fn extract_links(text: &str) -> Vec<VideoLink> {
+    let mut links: Vec<VideoLink> = Vec::new();
+    let mut push = |link: VideoLink| {
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    };
+
+    let mut index = 0;
+    while index < text.len() {
+        let rest = &text[index..];
+        // 单独的 BV 号和 av 号前面不能是字母或数字，避免匹配到单词中间
+        let boundary = text[..index]
+            .chars()
+            .last()
+            .is_none_or(|c| !c.is_ascii_alphanumeric());
+
+        if let Some(path) = rest.strip_prefix(VIDEO_PATH) {
+            if let Some((bvid, len)) = parse_id(path, false) {
+                let query = &path[len..][..query_len(&path[len..])];
+                push(VideoLink {
+                    bvid,
+                    part: parse_part(query),
+                });
+                index += VIDEO_PATH.len() + len + query.len();
+                continue;
+            }
+        } else if boundary {
+            if let Some((bvid, len)) = parse_id(rest, true) {
+                push(VideoLink { bvid, part: None });
+                index += len;
+                continue;
+            }
+        }
+        index += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    links
+}
+
+/// 找出消息中的短链接，返回 `https://` 开头、去掉参数的链接
+pub fn extract_short_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    for host in SHORT_HOSTS {
+        for (start, _) in text.match_indices(host) {
+            let path = &text[start + host.len()..];
+            let len = path
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(path.len());
+            // b23.tv/BV1xx411c7mD 这种直接带了 BV 号，不用再请求
+            if len == 0 || parse_id(&path[..len], false).is_some() {
+                continue;
+            }
+            let link = format!("https://{}{}", host, &path[..len]);
+            if !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+/// 从 QQ 分享卡片里取出跳转链接
+/// - 小程序 (LightApp) 是 JSON，链接在 `meta.detail_1.qqdocurl` 或 `meta.news.jumpUrl`
+/// - 旧版的结构化消息 (RichMsg) 是 XML，链接在 `url` 属性里
+pub fn card_url(content: &str) -> Option<String> {
+    if let Ok(value) = json::parse(content) {
+        let meta = &value["meta"];
+        return [&meta["detail_1"]["qqdocurl"], &meta["news"]["jumpUrl"]]
+            .into_iter()
+            .filter_map(JsonValue::as_str)
+            .find(|url| !url.is_empty())
+            .map(str::to_string);
+    }
+    let start = content.find(" url=\"")? + " url=\"".len();
+    let len = content[start..].find('"')?;
+    let url = content[start..start + len].replace("&amp;", "&");
+    Some(url).filter(|url| !url.is_empty())
+}
+
+/// 请求短链接但是不跟随跳转，从 `Location` 里找出视频链接
+pub async fn resolve_short_link(url: &str, timeouts: &Timeouts) -> reqwest::Result<Vec<VideoLink>> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.total)
+        .build()?;
+    let response = client.get(url).send().await?;
+    Ok(response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(extract_links)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_test() {
+        assert_eq!(av_to_bv(170001).as_deref(), Some("BV17x411w7KC"));
+        assert_eq!(bv_to_av("BV17x411w7KC"), Some(170001));
+        assert_eq!(bv_to_av("BV1xx411c7mD"), Some(2));
+        for aid in [1, 2, 170001, 455017605, 1054803170, MAX_AID - 1] {
+            assert_eq!(bv_to_av(&av_to_bv(aid).unwrap()), Some(aid));
+        }
+        assert_eq!(av_to_bv(0), None);
+        assert_eq!(av_to_bv(MAX_AID), None);
+        assert_eq!(bv_to_av("BV17x411w7K"), None);
+        assert_eq!(bv_to_av("BV17x411w7K0"), None);
+    }
+
+    #[test]
+    fn extract_links_test() {
+        let link = |bvid: &str, part: Option<u32>| VideoLink {
+            bvid: bvid.to_string(),
+            part,
+        };
+        let cases: Vec<(&str, Vec<VideoLink>)> = vec![
+            (
+                "https://www.bilibili.com/video/BV17x411w7KC",
+                vec![link("BV17x411w7KC", None)],
+            ),
+            (
+                "快看 https://www.bilibili.com/video/BV17x411w7KC/ 笑死",
+                vec![link("BV17x411w7KC", None)],
+            ),
+            (
+                "http://bilibili.com/video/BV17x411w7KC?p=2",
+                vec![link("BV17x411w7KC", Some(2))],
+            ),
+            (
+                "https://m.bilibili.com/video/BV17x411w7KC/?spm_id_from=333.1007&p=3#reply",
+                vec![link("BV17x411w7KC", Some(3))],
+            ),
+            (
+                "www.bilibili.com/video/av170001?p=0",
+                vec![link("BV17x411w7KC", None)],
+            ),
+            ("看看 av170001 吧", vec![link("BV17x411w7KC", None)]),
+            (
+                "https://www.bilibili.com/video/AV170001",
+                vec![link("BV17x411w7KC", None)],
+            ),
+            ("BV1xx411c7mD", vec![link("BV1xx411c7mD", None)]),
+            (
+                "https://www.bilibili.com/video/BV1xx411c7mD?p=1 和 https://www.bilibili.com/video/BV17x411w7KC",
+                vec![link("BV1xx411c7mD", Some(1)), link("BV17x411w7KC", None)],
+            ),
+            // 同一个视频只回复一次，不同的分 P 算不同的链接
+            (
+                "BV17x411w7KC av170001 https://www.bilibili.com/video/BV17x411w7KC?p=2",
+                vec![link("BV17x411w7KC", None), link("BV17x411w7KC", Some(2))],
+            ),
+            // 单词中间、太短或者不合法的都不算
+            ("have123 nav170001 xBV17x411w7KC", vec![]),
+            ("https://www.bilibili.com/video/", vec![]),
+            ("https://www.bilibili.com/video/BV17x411w7K", vec![]),
+            ("av0 av", vec![]),
+            // 链接之外的 av 号必须是小写并且足够长
+            ("AV1 编码", vec![]),
+            ("av2", vec![]),
+            ("av1234 和 Av170001", vec![]),
+            ("AV170001", vec![]),
+            ("https://www.bilibili.com/bangumi/play/ep1", vec![]),
+        ];
+        for (text, links) in cases {
+            assert_eq!(extract_links(text), links, "{}", text);
+        }
+    }
+
+    #[test]
+    fn extract_short_links_test() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            (
+                "【标题】 https://b23.tv/AbCd123?share_source=qq",
+                vec!["https://b23.tv/AbCd123"],
+            ),
+            (
+                "b23.tv/AbCd123 b23.tv/AbCd123 https://bili2233.cn/xYz9",
+                vec!["https://b23.tv/AbCd123", "https://bili2233.cn/xYz9"],
+            ),
+            ("https://b23.tv/BV17x411w7KC", vec![]),
+            ("https://b23.tv/ b23.tv", vec![]),
+        ];
+        for (text, links) in cases {
+            assert_eq!(extract_short_links(text), links, "{}", text);
+        }
+    }
+
+    #[test]
+    fn card_url_test() {
+        let light_app = r#"{"app":"com.tencent.miniapp_01","meta":{"detail_1":{"appid":"1109937557","title":"哔哩哔哩","desc":"视频标题","url":"m.q.qpic.cn\/cover","qqdocurl":"https:\/\/b23.tv\/AbCd123?share_medium=android"}}}"#;
+        assert_eq!(
+            card_url(light_app).as_deref(),
+            Some("https://b23.tv/AbCd123?share_medium=android")
+        );
+        let news = r#"{"app":"com.tencent.structmsg","meta":{"news":{"tag":"哔哩哔哩","jumpUrl":"https://www.bilibili.com/video/BV17x411w7KC?p=2"}}}"#;
+        assert_eq!(
+            extract_links(&card_url(news).unwrap()),
+            vec![VideoLink {
+                bvid: String::from("BV17x411w7KC"),
+                part: Some(2)
+            }]
+        );
+        let rich_msg = r#"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="1" templateID="1" action="web" brief="[哔哩哔哩]" url="https://b23.tv/AbCd123?a=1&amp;b=2"><item layout="2"><title>视频标题</title></item></msg>"#;
+        assert_eq!(
+            card_url(rich_msg).as_deref(),
+            Some("https://b23.tv/AbCd123?a=1&b=2")
+        );
+        assert_eq!(card_url(r#"{"app":"com.tencent.weather"}"#), None);
+        assert_eq!(card_url("<msg url=\"\"></msg>"), None);
+        assert_eq!(card_url(""), None);
+    }
+
+    #[test]
+    fn display_test() {
+        let link = VideoLink {
+            bvid: String::from("BV17x411w7KC"),
+            part: Some(2),
+        };
+        assert_eq!(
+            link.to_string(),
+            "https://www.bilibili.com/video/BV17x411w7KC?p=2"
+        );
+        let link = VideoLink { part: None, ..link };
+        assert_eq!(
+            link.to_string(),
+            "https://www.bilibili.com/video/BV17x411w7KC"
+        );
+    }
+}
@@ -0,0 +1,5 @@
+// B 站视频链接的解析和预览
+
+pub mod cache;
+pub mod link;
+pub mod view;
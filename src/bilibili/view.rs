@@ -0,0 +1,316 @@
+use super::link::VideoLink;
+use crate::time::format_date;
+use json::JsonValue;
+use std::fmt;
+
+// x/web-interface/view 接口返回的视频信息和预览消息里的字段
+
+const VIEW_API: &str = "https://api.bilibili.com/x/web-interface/view";
+
+/// 简介最多保留这么多个字符
+const DESC_CHARS: usize = 80;
+
+/// 接口的错误码，参考 https://github.com/SocialSisterYi/bilibili-API-collect
+pub const NOT_FOUND: i64 = -404;
+pub const FORBIDDEN: i64 = -403;
+/// 请求太频繁被风控，HTTP 状态码也是 412
+pub const RATE_LIMITED: i64 = -412;
+/// 稿件不可见，一般是被删除了
+pub const INVISIBLE: i64 = 62002;
+/// 稿件审核中
+pub const REVIEWING: i64 = 62004;
+/// 仅 UP 主自己可见
+pub const PRIVATE: i64 = 62012;
+
+#[derive(Debug)]
+pub enum ViewError {
+    Http(reqwest::Error),
+    /// 返回的不是 JSON，比如被风控时的 HTML 页面
+    Json(json::Error),
+    /// 接口返回的 `code` 不为 0
+    Api {
+        code: i64,
+        message: String,
+    },
+    /// 缺少必要的字段
+    Missing(&'static str),
+}
+
+impl ViewError {
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            ViewError::Api { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViewError::Http(e) => write!(f, "request error: {}", e),
+            ViewError::Json(e) => write!(f, "parse response error: {}", e),
+            ViewError::Api { code, message } => write!(f, "api error {}: {}", code, message),
+            ViewError::Missing(field) => write!(f, "{} is missing in response", field),
+        }
+    }
+}
+
+impl std::error::Error for ViewError {}
+
+impl From<reqwest::Error> for ViewError {
+    fn from(e: reqwest::Error) -> Self {
+        ViewError::Http(e)
+    }
+}
+
+impl From<json::Error> for ViewError {
+    fn from(e: json::Error) -> Self {
+        ViewError::Json(e)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoInfo {
+    pub title: String,
+    /// 封面图片的链接
+    pub cover: String,
+    /// UP 主的名字
+    pub owner: String,
+    /// 总时长，单位为秒
+    pub duration: u64,
+    /// 发布时间，unix 时间戳
+    pub pubdate: u64,
+    /// 分区名
+    pub partition: String,
+    pub desc: String,
+    pub view: u64,
+    pub like: u64,
+    pub coin: u64,
+    pub favorite: u64,
+}
+
+impl VideoInfo {
+    /// 解析接口返回的 `data`，标题和封面是必须的，其余缺少的字段使用默认值
+    pub fn from_json(data: &JsonValue) -> Result<VideoInfo, ViewError> {
+        let string = |value: &JsonValue| value.as_str().unwrap_or_default().to_string();
+        let required = |key: &'static str| {
+            data[key]
+                .as_str()
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+                .ok_or(ViewError::Missing(key))
+        };
+        let stat = &data["stat"];
+        Ok(VideoInfo {
+            title: required("title")?,
+            cover: required("pic")?,
+            owner: string(&data["owner"]["name"]),
+            duration: data["duration"].as_u64().unwrap_or_default(),
+            pubdate: data["pubdate"].as_u64().unwrap_or_default(),
+            partition: string(&data["tname"]),
+            desc: string(&data["desc"]),
+            view: stat["view"].as_u64().unwrap_or_default(),
+            like: stat["like"].as_u64().unwrap_or_default(),
+            coin: stat["coin"].as_u64().unwrap_or_default(),
+            favorite: stat["favorite"].as_u64().unwrap_or_default(),
+        })
+    }
+
+    /// 和接口返回的 `data` 格式相同，可以用 `from_json` 读回来
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {
+            "title": self.title.as_str(),
+            "pic": self.cover.as_str(),
+            "owner": {"name": self.owner.as_str()},
+            "duration": self.duration,
+            "pubdate": self.pubdate,
+            "tname": self.partition.as_str(),
+            "desc": self.desc.as_str(),
+            "stat": {
+                "view": self.view,
+                "like": self.like,
+                "coin": self.coin,
+                "favorite": self.favorite,
+            },
+        }
+    }
+
+    /// 解析整个响应 `{"code": 0, "message": "0", "data": {...}}`
+    pub fn parse(text: &str) -> Result<VideoInfo, ViewError> {
+        let value = json::parse(text)?;
+        let code = value["code"].as_i64().ok_or(ViewError::Missing("code"))?;
+        if code != 0 {
+            return Err(ViewError::Api {
+                code,
+                message: value["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        VideoInfo::from_json(&value["data"])
+    }
+
+    pub async fn fetch(client: &reqwest::Client, bvid: &str) -> Result<VideoInfo, ViewError> {
+        let response = client.get(VIEW_API).query(&[("bvid", bvid)]).send().await?;
+        // 被风控时返回的是 HTML 页面
+        if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+            return Err(ViewError::Api {
+                code: RATE_LIMITED,
+                message: response.status().to_string(),
+            });
+        }
+        VideoInfo::parse(&response.text().await?)
+    }
+
+    /// 预览模板里可以使用的字段
+    pub fn values(&self, link: &VideoLink) -> Vec<(&'static str, String)> {
+        vec![
+            ("url", link.to_string()),
+            ("bvid", link.bvid.clone()),
+            ("title", self.title.clone()),
+            ("owner", self.owner.clone()),
+            ("duration", format_duration(self.duration)),
+            ("pubdate", format_date(self.pubdate)),
+            ("partition", self.partition.clone()),
+            ("desc", truncate(self.desc.trim(), DESC_CHARS)),
+            ("view", format_count(self.view)),
+            ("like", format_count(self.like)),
+            ("coin", format_count(self.coin)),
+            ("favorite", format_count(self.favorite)),
+        ]
+    }
+}
+
+/// `3:05`，超过一小时时为 `1:02:03`
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+/// 和 B 站一样，一万以上显示为 `1.2万`，一亿以上显示为 `3.4亿`
+pub fn format_count(count: u64) -> String {
+    match count {
+        0..=9_999 => count.to_string(),
+        // 四舍五入后会变成 10000.0万 的也显示为亿
+        10_000..=99_994_999 => format!("{:.1}万", count as f64 / 10_000.0),
+        _ => format!("{:.1}亿", count as f64 / 100_000_000.0),
+    }
+}
+
+/// 超过 `max_chars` 个字符时截断并加上省略号，换行换成空格
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut result: String = text.chars().take(max_chars).collect();
+    result.push('…');
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_test() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(185), "3:05");
+        assert_eq!(format_duration(3723), "1:02:03");
+        assert_eq!(format_count(9999), "9999");
+        assert_eq!(format_count(12345), "1.2万");
+        assert_eq!(format_count(99_994_999), "9999.5万");
+        assert_eq!(format_count(99_999_999), "1.0亿");
+        assert_eq!(format_count(340_000_000), "3.4亿");
+        assert_eq!(truncate("一二三", 3), "一二三");
+        assert_eq!(truncate("一二三四", 3), "一二三…");
+        assert_eq!(truncate("第一行\n\n第二行", 10), "第一行 第二行");
+    }
+
+    #[test]
+    fn parse_test() {
+        let info = VideoInfo::parse(
+            r#"{"code": 0, "message": "0", "ttl": 1, "data": {"bvid": "BV17x411w7KC", "title": "标题", "pic": "http://i0.hdslb.com/cover.jpg"}}"#,
+        )
+        .unwrap();
+        assert_eq!(info.title, "标题");
+        assert_eq!(info.view, 0);
+
+        let error = VideoInfo::parse(r#"{"code": -404, "message": "啥都木有", "ttl": 1}"#);
+        assert!(matches!(
+            error,
+            Err(ViewError::Api { code: NOT_FOUND, ref message }) if message == "啥都木有"
+        ));
+        assert_eq!(
+            VideoInfo::parse(r#"{"code": 62012, "message": "稿件不可见"}"#)
+                .unwrap_err()
+                .code(),
+            Some(PRIVATE)
+        );
+        assert!(matches!(
+            VideoInfo::parse("<html>412 Precondition Failed</html>"),
+            Err(ViewError::Json(_))
+        ));
+        assert!(matches!(
+            VideoInfo::parse(r#"{"message": "0"}"#),
+            Err(ViewError::Missing("code"))
+        ));
+        assert!(matches!(
+            VideoInfo::parse(r#"{"code": 0, "data": {"title": "标题", "pic": ""}}"#),
+            Err(ViewError::Missing("pic"))
+        ));
+    }
+
+    #[test]
+    fn values_test() {
+        let data = json::parse(
+            r#"{
+                "bvid": "BV17x411w7KC",
+                "title": "【MV】保加利亚妖王AZIS视频合辑",
+                "pic": "http://i0.hdslb.com/bfs/archive/cover.jpg",
+                "tname": "音乐综合",
+                "pubdate": 1320850533,
+                "desc": "简介\n第二行",
+                "duration": 2412,
+                "owner": {"mid": 122541, "name": "冰封.虾子"},
+                "stat": {"view": 4380839, "favorite": 75089, "coin": 20512, "like": 60011}
+            }"#,
+        )
+        .unwrap();
+        let info = VideoInfo::from_json(&data).unwrap();
+        assert_eq!(info.cover, "http://i0.hdslb.com/bfs/archive/cover.jpg");
+        assert_eq!(VideoInfo::from_json(&info.to_json()).unwrap(), info);
+        let link = VideoLink {
+            bvid: String::from("BV17x411w7KC"),
+            part: Some(2),
+        };
+        let values = info.values(&link);
+        let value = |key: &str| {
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(
+            value("url"),
+            Some("https://www.bilibili.com/video/BV17x411w7KC?p=2")
+        );
+        assert_eq!(value("owner"), Some("冰封.虾子"));
+        assert_eq!(value("duration"), Some("40:12"));
+        assert_eq!(value("pubdate"), Some("2011-11-09"));
+        assert_eq!(value("partition"), Some("音乐综合"));
+        assert_eq!(value("desc"), Some("简介 第二行"));
+        assert_eq!(value("view"), Some("438.1万"));
+        assert_eq!(value("like"), Some("6.0万"));
+        assert_eq!(value("coin"), Some("2.1万"));
+        assert_eq!(value("favorite"), Some("7.5万"));
+
+        assert!(matches!(
+            VideoInfo::from_json(&JsonValue::Null),
+            Err(ViewError::Missing("title"))
+        ));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::store::{load_json, save_json, StoreError};
+use json::JsonValue;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// 每个群绑定的服务器别名，保存在 bindings.json 中
+// {"群号": {"default": "survival", "watch": false, "card": false, "servers": {"survival": "mc.example.com"}}}
+
+pub const BINDINGS_PATH: &str = "bindings.json";
+
+const MAX_ALIAS_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupBindings {
+    pub default: Option<String>,
+    /// 是否在群里播报服务器的玩家进出和离线
+    pub watch: bool,
+    /// `/mcping` 是否回复渲染好的状态卡片图片
+    pub card: bool,
+    /// 别名 -> `host[:port]`
+    pub servers: BTreeMap<String, String>,
+}
+
+impl GroupBindings {
+    /// 没有绑定也没有开启任何设置，可以删掉
+    fn is_empty(&self) -> bool {
+        self.servers.is_empty() && !self.watch && !self.card
+    }
+}
+
+/// 某个群里绑定的一个服务器
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundServer {
+    pub group: i64,
+    pub alias: String,
+    pub watch: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Bindings {
+    path: Option<PathBuf>,
+    groups: BTreeMap<i64, GroupBindings>,
+}
+
+/// 别名只能包含字母、数字、`-` 和 `_`，这样就不会和地址混淆
+pub fn is_valid_alias(alias: &str) -> bool {
+    !alias.is_empty()
+        && alias.len() <= MAX_ALIAS_LENGTH
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Bindings {
+    pub fn from_json(value: &JsonValue) -> Result<Bindings, StoreError> {
+        let mut bindings = Bindings::default();
+        for (group, entry) in value.entries() {
+            let group: i64 = group
+                .parse()
+                .map_err(|_| StoreError::Invalid(format!("invalid group {:?}", group)))?;
+            let mut group_bindings = GroupBindings {
+                default: entry["default"].as_str().map(|alias| alias.to_string()),
+                watch: entry["watch"].as_bool().unwrap_or_default(),
+                card: entry["card"].as_bool().unwrap_or_default(),
+                servers: BTreeMap::new(),
+            };
+            for (alias, address) in entry["servers"].entries() {
+                let address = address.as_str().ok_or_else(|| {
+                    StoreError::Invalid(format!("address of {} is not a string", alias))
+                })?;
+                group_bindings
+                    .servers
+                    .insert(alias.to_string(), address.to_string());
+            }
+            bindings.groups.insert(group, group_bindings);
+        }
+        Ok(bindings)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut value = JsonValue::new_object();
+        for (group, group_bindings) in &self.groups {
+            let mut servers = JsonValue::new_object();
+            for (alias, address) in &group_bindings.servers {
+                servers[alias.as_str()] = address.as_str().into();
+            }
+            value[group.to_string()] = json::object! {
+                "default": group_bindings.default.clone(),
+                "watch": group_bindings.watch,
+                "card": group_bindings.card,
+                "servers": servers,
+            };
+        }
+        value
+    }
+
+    /// 文件不存在时返回空的绑定，之后的修改会保存到 `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings, StoreError> {
+        let mut bindings = match load_json(&path)? {
+            Some(value) => Bindings::from_json(&value)?,
+            None => Bindings::default(),
+        };
+        bindings.path = Some(path.as_ref().to_path_buf());
+        Ok(bindings)
+    }
+
+    fn save(&self) -> Result<(), StoreError> {
+        match &self.path {
+            Some(path) => Ok(save_json(path, &self.to_json())?),
+            None => Ok(()),
+        }
+    }
+
+    pub fn group(&self, group: i64) -> Option<&GroupBindings> {
+        self.groups.get(&group)
+    }
+
+    /// 绑定或覆盖一个别名，群里的第一个绑定会成为默认服务器
+    pub fn bind(&mut self, group: i64, alias: &str, address: &str) -> Result<(), StoreError> {
+        if !is_valid_alias(alias) {
+            return Err(StoreError::Invalid(format!("invalid alias {:?}", alias)));
+        }
+        let group_bindings = self.groups.entry(group).or_default();
+        group_bindings
+            .servers
+            .insert(alias.to_string(), address.to_string());
+        if group_bindings.default.is_none() {
+            group_bindings.default = Some(alias.to_string());
+        }
+        self.save()
+    }
+
+    /// 返回被删除的地址，删掉默认服务器时改用剩下的第一个
+    pub fn unbind(&mut self, group: i64, alias: &str) -> Result<Option<String>, StoreError> {
+        let group_bindings = match self.groups.get_mut(&group) {
+            Some(group_bindings) => group_bindings,
+            None => return Ok(None),
+        };
+        let address = match group_bindings.servers.remove(alias) {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        if group_bindings.default.as_deref() == Some(alias) {
+            group_bindings.default = group_bindings.servers.keys().next().cloned();
+        }
+        if group_bindings.is_empty() {
+            self.groups.remove(&group);
+        }
+        self.save()?;
+        Ok(Some(address))
+    }
+
+    /// 别名不存在时返回 `false`
+    pub fn set_default(&mut self, group: i64, alias: &str) -> Result<bool, StoreError> {
+        match self.groups.get_mut(&group) {
+            Some(group_bindings) if group_bindings.servers.contains_key(alias) => {
+                group_bindings.default = Some(alias.to_string());
+                self.save()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn set_watch(&mut self, group: i64, watch: bool) -> Result<(), StoreError> {
+        self.groups.entry(group).or_default().watch = watch;
+        self.save()
+    }
+
+    pub fn set_card(&mut self, group: i64, card: bool) -> Result<(), StoreError> {
+        self.groups.entry(group).or_default().card = card;
+        self.save()
+    }
+
+    /// 所有群绑定的服务器，按地址合并
+    pub fn servers(&self) -> BTreeMap<String, Vec<BoundServer>> {
+        let mut servers: BTreeMap<String, Vec<BoundServer>> = BTreeMap::new();
+        for (group, group_bindings) in &self.groups {
+            for (alias, address) in &group_bindings.servers {
+                servers
+                    .entry(address.clone())
+                    .or_default()
+                    .push(BoundServer {
+                        group: *group,
+                        alias: alias.clone(),
+                        watch: group_bindings.watch,
+                    });
+            }
+        }
+        servers
+    }
+
+    /// 查找别名对应的地址，`alias` 为 `None` 时返回默认服务器
+    pub fn lookup(&self, group: i64, alias: Option<&str>) -> Option<(&str, &str)> {
+        let group_bindings = self.groups.get(&group)?;
+        let alias = match alias {
+            Some(alias) => alias,
+            None => group_bindings.default.as_deref()?,
+        };
+        group_bindings
+            .servers
+            .get_key_value(alias)
+            .map(|(alias, address)| (alias.as_str(), address.as_str()))
+    }
+}
+
+static BINDINGS: OnceLock<Mutex<Bindings>> = OnceLock::new();
+
+/// 第一次使用时从 `BINDINGS_PATH` 读取
+///
+/// 读取失败时记录日志并使用空的绑定，这时不再保存，避免覆盖掉原来的文件
+pub fn global() -> &'static Mutex<Bindings> {
+    BINDINGS.get_or_init(|| {
+        let bindings = Bindings::load(BINDINGS_PATH).unwrap_or_else(|e| {
+            tracing::error!(
+                "load {} error: {}, changes will not be saved until it is fixed",
+                BINDINGS_PATH,
+                e
+            );
+            Bindings::default()
+        });
+        Mutex::new(bindings)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bind_test() {
+        let mut bindings = Bindings::default();
+        bindings.bind(1, "survival", "mc.example.com").unwrap();
+        bindings
+            .bind(1, "creative", "mc.example.com:25566")
+            .unwrap();
+        assert_eq!(
+            bindings.lookup(1, None),
+            Some(("survival", "mc.example.com"))
+        );
+        assert_eq!(
+            bindings.lookup(1, Some("creative")),
+            Some(("creative", "mc.example.com:25566"))
+        );
+        assert_eq!(bindings.lookup(1, Some("missing")), None);
+        assert_eq!(bindings.lookup(2, None), None);
+        assert!(bindings.bind(1, "mc.example.com", "x").is_err());
+
+        assert!(bindings.set_default(1, "creative").unwrap());
+        assert!(!bindings.set_default(1, "missing").unwrap());
+        assert_eq!(bindings.lookup(1, None).unwrap().0, "creative");
+
+        assert_eq!(
+            bindings.unbind(1, "creative").unwrap().as_deref(),
+            Some("mc.example.com:25566")
+        );
+        assert_eq!(bindings.lookup(1, None).unwrap().0, "survival");
+        assert_eq!(bindings.unbind(1, "creative").unwrap(), None);
+        bindings.unbind(1, "survival").unwrap();
+        assert!(bindings.group(1).is_none());
+    }
+
+    #[test]
+    fn servers_test() {
+        let mut bindings = Bindings::default();
+        bindings.bind(1, "survival", "mc.example.com").unwrap();
+        bindings.bind(2, "main", "mc.example.com").unwrap();
+        bindings
+            .bind(2, "creative", "creative.example.com")
+            .unwrap();
+        bindings.bind(3, "other", "other.example.com").unwrap();
+        bindings.set_watch(2, true).unwrap();
+
+        let servers = bindings.servers();
+        assert_eq!(servers.len(), 3);
+        assert_eq!(
+            servers["mc.example.com"],
+            vec![
+                BoundServer {
+                    group: 1,
+                    alias: String::from("survival"),
+                    watch: false,
+                },
+                BoundServer {
+                    group: 2,
+                    alias: String::from("main"),
+                    watch: true,
+                },
+            ]
+        );
+
+        bindings.set_watch(1, true).unwrap();
+
+        // 开启播报的群即使解绑了所有服务器也保留设置
+        bindings.unbind(1, "survival").unwrap();
+        assert!(bindings.group(1).unwrap().watch);
+    }
+
+    #[test]
+    fn persist_test() {
+        let path =
+            std::env::temp_dir().join(format!("qq-bot-bindings-{}.json", std::process::id()));
+        let mut bindings = Bindings::load(&path).unwrap();
+        bindings.bind(123456, "survival", "mc.example.com").unwrap();
+        bindings.bind(123456, "creative", "[::1]:25566").unwrap();
+        bindings.set_card(123456, true).unwrap();
+
+        let loaded = Bindings::load(&path).unwrap();
+        assert_eq!(loaded.group(123456), bindings.group(123456));
+        assert!(loaded.group(123456).unwrap().card);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Bindings::from_json(&json::object! {"abc": {}}).is_err());
+    }
+}
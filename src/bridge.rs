@@ -0,0 +1,333 @@
+use crate::mc_protocol::is_valid_player_name;
+use crate::mc_protocol::rcon::MAX_REQUEST_BODY;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::PathBuf;
+
+// 群和服务器之间的聊天互通
+// 群消息通过 RCON 的 tellraw 发到游戏里，游戏里的消息从服务器的 latest.log 读出来发到群里
+
+/// 原版死亡消息中玩家名后面的部分，参考 https://minecraft.wiki/w/Death_messages
+const DEATH_MESSAGES: [&str; 36] = [
+    "was shot by",
+    "was pummeled by",
+    "was pricked to death",
+    "walked into a cactus",
+    "drowned",
+    "experienced kinetic energy",
+    "blew up",
+    "was blown up by",
+    "was killed by",
+    "hit the ground too hard",
+    "fell from a high place",
+    "fell off",
+    "fell while climbing",
+    "was impaled",
+    "was squashed by",
+    "was skewered by",
+    "went up in flames",
+    "walked into fire",
+    "burned to death",
+    "was burnt to a crisp",
+    "went off with a bang",
+    "tried to swim in lava",
+    "was struck by lightning",
+    "discovered the floor was lava",
+    "walked into the danger zone",
+    "froze to death",
+    "was frozen to death by",
+    "starved to death",
+    "suffocated in a wall",
+    "was squished too much",
+    "was poked to death",
+    "fell out of the world",
+    "withered away",
+    "was slain by",
+    "was fireballed by",
+    "died",
+];
+
+const ADVANCEMENTS: [&str; 3] = [
+    "has made the advancement",
+    "has completed the challenge",
+    "has reached the goal",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEvent {
+    Chat {
+        player: String,
+        message: String,
+    },
+    Join(String),
+    Leave(String),
+    /// 完整的死亡消息
+    Death(String),
+    Advancement {
+        player: String,
+        advancement: String,
+    },
+}
+
+impl LogEvent {
+    /// 配置里用来选择转发哪些事件的名字
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LogEvent::Chat { .. } => "chat",
+            LogEvent::Join(_) => "join",
+            LogEvent::Leave(_) => "leave",
+            LogEvent::Death(_) => "death",
+            LogEvent::Advancement { .. } => "advancement",
+        }
+    }
+}
+
+/// 解析一行日志，原版是 `[12:00:00] [Server thread/INFO]: <Steve> hi`，
+/// Paper 等服务端是 `[12:00:00 INFO]: <Steve> hi`
+pub fn parse_log_line(line: &str) -> Option<LogEvent> {
+    let (head, text) = line.trim_end().split_once("]: ")?;
+    if !head.ends_with("INFO") || !head.starts_with('[') {
+        return None;
+    }
+    // 1.19 以后没有签名的消息前面有 [Not Secure]
+    let text = text.strip_prefix("[Not Secure] ").unwrap_or(text);
+
+    if let Some(rest) = text.strip_prefix('<') {
+        let (player, message) = rest.split_once("> ")?;
+        return Some(LogEvent::Chat {
+            player: player.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    let (player, rest) = text.split_once(' ')?;
+    if !is_valid_player_name(player) {
+        return None;
+    }
+    if rest == "joined the game" {
+        return Some(LogEvent::Join(player.to_string()));
+    }
+    if rest == "left the game" {
+        return Some(LogEvent::Leave(player.to_string()));
+    }
+    for prefix in ADVANCEMENTS {
+        if let Some(advancement) = rest.strip_prefix(prefix) {
+            return Some(LogEvent::Advancement {
+                player: player.to_string(),
+                advancement: advancement.trim().to_string(),
+            });
+        }
+    }
+    DEATH_MESSAGES
+        .iter()
+        .any(|death| {
+            rest.strip_prefix(death)
+                .is_some_and(|end| end.is_empty() || end.starts_with(' '))
+        })
+        .then(|| LogEvent::Death(text.to_string()))
+}
+
+/// 含有屏蔽词的消息两个方向都不转发
+pub fn is_blocked(text: &str, blocked: &[String]) -> bool {
+    let text = text.to_lowercase();
+    blocked
+        .iter()
+        .any(|word| !word.is_empty() && text.contains(&word.to_lowercase()))
+}
+
+/// 截断到不超过 `max` 字节，不会切开多字节字符
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// `tellraw @a [{"text":"[QQ] ","color":"aqua"},{"text":"<名片> "},{"text":"消息"}]`
+///
+/// 整个命令不能超过 RCON 的 1446 字节，引号、反斜杠和控制字符转义后会变长，
+/// 所以按转义后的长度截断消息
+pub fn tellraw_command(prefix: &str, sender: &str, message: &str) -> String {
+    let build = |message: &str| {
+        let component = json::array![
+            json::object! {"text": format!("[{}] ", prefix), "color": "aqua"},
+            json::object! {"text": format!("<{}> ", sender)},
+            json::object! {"text": message},
+        ];
+        format!("tellraw @a {}", component.dump())
+    };
+    let command = build(message);
+    if command.len() <= MAX_REQUEST_BODY {
+        return command;
+    }
+    // 二分查找转义后还放得下的最长前缀
+    let (mut low, mut high) = (0, message.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if build(truncate(message, mid)).len() <= MAX_REQUEST_BODY {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    build(truncate(message, low))
+}
+
+/// 跟踪不断追加的日志文件，服务器重启后 latest.log 会被换成新文件
+pub struct LogTail {
+    path: PathBuf,
+    /// 第一次读取之前为 `None`，这时跳到文件末尾，不转发以前的日志
+    offset: Option<u64>,
+}
+
+impl LogTail {
+    pub fn new<P: Into<PathBuf>>(path: P) -> LogTail {
+        LogTail {
+            path: path.into(),
+            offset: None,
+        }
+    }
+
+    /// 读取上次之后新增的完整的行
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        let offset = match self.offset {
+            // 文件变短了说明换了新文件，从头开始读
+            Some(offset) if offset <= len => offset,
+            Some(_) => 0,
+            None => {
+                self.offset = Some(len);
+                return Ok(Vec::new());
+            }
+        };
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut reader = BufReader::new(file);
+        let mut lines: Vec<String> = Vec::new();
+        let mut read = offset;
+        let mut buf: Vec<u8> = Vec::new();
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            // 没有换行的是还没写完的行，下次再读
+            if n == 0 || buf.last() != Some(&b'\n') {
+                break;
+            }
+            read += n as u64;
+            lines.push(String::from_utf8_lossy(&buf).trim_end().to_string());
+        }
+        self.offset = Some(read);
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn parse_log_line_test() {
+        let chat = |player: &str, message: &str| {
+            Some(LogEvent::Chat {
+                player: player.to_string(),
+                message: message.to_string(),
+            })
+        };
+        let cases = [
+            (
+                "[12:00:00] [Server thread/INFO]: <Steve> hello world",
+                chat("Steve", "hello world"),
+            ),
+            (
+                "[12:00:00 INFO]: [Not Secure] <Alex> 你好",
+                chat("Alex", "你好"),
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Steve joined the game",
+                Some(LogEvent::Join(String::from("Steve"))),
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Steve left the game",
+                Some(LogEvent::Leave(String::from("Steve"))),
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Steve was slain by Zombie",
+                Some(LogEvent::Death(String::from("Steve was slain by Zombie"))),
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Alex_2 drowned",
+                Some(LogEvent::Death(String::from("Alex_2 drowned"))),
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Steve has made the advancement [Stone Age]",
+                Some(LogEvent::Advancement {
+                    player: String::from("Steve"),
+                    advancement: String::from("[Stone Age]"),
+                }),
+            ),
+            // RCON 和 say 的消息、警告和其他日志都不转发
+            ("[12:00:00] [Server thread/INFO]: [Rcon] hello", None),
+            ("[12:00:00] [Server thread/WARN]: <Steve> hello", None),
+            (
+                "[12:00:00] [Server thread/INFO]: Steve lost connection: Disconnected",
+                None,
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Done (3.2s)! For help, type \"help\"",
+                None,
+            ),
+            (
+                "[12:00:00] [Server thread/INFO]: Preparing spawn area: died",
+                None,
+            ),
+        ];
+        for (line, event) in cases {
+            assert_eq!(parse_log_line(line), event, "{}", line);
+        }
+    }
+
+    #[test]
+    fn tellraw_test() {
+        assert_eq!(
+            tellraw_command("QQ", "小明", "say \"hi\""),
+            r#"tellraw @a [{"text":"[QQ] ","color":"aqua"},{"text":"<小明> "},{"text":"say \"hi\""}]"#
+        );
+        for long in ["好".repeat(1000), "\"\\".repeat(1000), "\u{1}".repeat(1000)] {
+            let command = tellraw_command("QQ", "小明", &long);
+            assert!(command.len() <= MAX_REQUEST_BODY, "{}", command.len());
+            // 尽量保留更多的内容
+            assert!(command.len() > MAX_REQUEST_BODY - 6, "{}", command.len());
+            assert!(json::parse(&command["tellraw @a ".len()..]).is_ok());
+        }
+        assert!(is_blocked("Buy CHEAP gold", &[String::from("cheap")]));
+        assert!(!is_blocked("hello", &[String::new()]));
+    }
+
+    #[test]
+    fn tail_test() {
+        let path = std::env::temp_dir().join(format!("qq-bot-latest-{}.log", std::process::id()));
+        std::fs::write(&path, "old line\n").unwrap();
+        let mut tail = LogTail::new(&path);
+        assert!(tail.poll().unwrap().is_empty());
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "first\nsecond\nthi").unwrap();
+        assert_eq!(tail.poll().unwrap(), vec!["first", "second"]);
+        writeln!(file, "rd").unwrap();
+        assert_eq!(tail.poll().unwrap(), vec!["third"]);
+
+        // 服务器重启后换成了新文件
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(tail.poll().unwrap(), vec!["new"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
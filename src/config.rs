@@ -0,0 +1,474 @@
+use crate::timeout::Timeouts;
+use json::JsonValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// 机器人的配置，默认从工作目录下的 config.json 读取
+
+pub const CONFIG_PATH: &str = "config.json";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Json(json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "read config error: {}", e),
+            ConfigError::Json(e) => write!(f, "parse config error: {}", e),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<json::Error> for ConfigError {
+    fn from(e: json::Error) -> Self {
+        ConfigError::Json(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RconServer {
+    pub address: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchConfig {
+    /// 两次 ping 之间的间隔
+    pub interval: Duration,
+    /// 连续失败这么多次才认为服务器离线，避免网络波动时刷屏
+    pub offline_threshold: u32,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            interval: Duration::from_secs(60),
+            offline_threshold: 3,
+        }
+    }
+}
+
+/// 一个群和一个服务器之间的聊天互通
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeConfig {
+    pub group: i64,
+    /// `rcon` 里配置的服务器名，群消息通过它发到游戏里
+    pub server: String,
+    /// 服务器的 latest.log，不配置时只把群消息转发到游戏
+    pub log: Option<String>,
+    /// 游戏里显示的消息前缀
+    pub prefix: String,
+    /// 转发到群里的事件：chat、join、leave、death、advancement
+    pub events: Vec<String>,
+    /// 含有这些词的消息不转发
+    pub blocked: Vec<String>,
+}
+
+/// 群里的白名单申请通过后加到哪个服务器
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhitelistConfig {
+    /// `rcon` 里配置的服务器名
+    pub server: String,
+    /// 绑定了账号的成员退群时直接移出白名单，否则只提醒管理员处理
+    /// 还在其它使用同一个服务器的群里时都会保留
+    pub auto_remove: bool,
+}
+
+/// 新成员入群时的欢迎消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct WelcomeConfig {
+    /// 可以使用 {nickname}、{uin}、{server}、{status}、{online}、{max}、{version}、{rules}
+    /// {server} 是本群默认服务器的地址
+    pub template: String,
+    pub rules: String,
+}
+
+pub const DEFAULT_WELCOME: &str =
+    "欢迎 {nickname} 加入本群！\n服务器地址：{server}\n服务器状态：{status}\n{rules}";
+
+/// B 站视频预览的消息模板，可以使用 {url}、{bvid}、{title}、{owner}、{duration}、{pubdate}、
+/// {partition}、{desc}、{view}、{like}、{coin}、{favorite}，封面图片总是附在最后
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoConfig {
+    /// 群号 -> 模板，没有配置的群使用 `DEFAULT_VIDEO`
+    pub templates: HashMap<i64, String>,
+    /// 视频信息和封面缓存多久
+    pub cache_ttl: Duration,
+    /// 是否把缓存保存到文件，重启后继续使用
+    pub persist_cache: bool,
+    /// 同一个群里同一个视频在这段时间内只预览一次，为 0 时不限制
+    pub cooldown: Duration,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        VideoConfig {
+            templates: HashMap::new(),
+            cache_ttl: Duration::from_secs(3600),
+            persist_cache: false,
+            cooldown: Duration::from_secs(600),
+        }
+    }
+}
+
+impl VideoConfig {
+    pub fn template(&self, group: i64) -> &str {
+        self.templates
+            .get(&group)
+            .map_or(DEFAULT_VIDEO, |template| template.as_str())
+    }
+}
+
+pub const DEFAULT_VIDEO: &str = "{url}\n{title}\nUP主：{owner}  时长：{duration}  {pubdate} 发布\n分区：{partition}\n播放 {view}  点赞 {like}  投币 {coin}  收藏 {favorite}\n{desc}";
+
+const BRIDGE_EVENTS: [&str; 5] = ["chat", "join", "leave", "death", "advancement"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// 可以使用管理命令的 QQ 号
+    pub admins: Vec<i64>,
+    pub rcon: HashMap<String, RconServer>,
+    /// 各命令的超时设置，`default` 是没有单独配置的命令使用的值
+    pub timeouts: HashMap<String, Timeouts>,
+    pub watch: WatchConfig,
+    /// 图表和状态卡片使用的字体文件，缺省时在常见的系统字体中查找
+    pub font: Option<String>,
+    pub bridges: Vec<BridgeConfig>,
+    /// 群号 -> 白名单设置，没有配置的群不能申请白名单
+    pub whitelist: HashMap<i64, WhitelistConfig>,
+    /// 群号 -> 欢迎消息，没有配置的群不发送
+    pub welcome: HashMap<i64, WelcomeConfig>,
+    pub video: VideoConfig,
+}
+
+fn invalid(msg: String) -> ConfigError {
+    ConfigError::Invalid(msg)
+}
+
+/// `{"connect": 3000, "read": 5000, "total": 10000}`，单位为毫秒，缺省的字段沿用 `base`
+fn parse_timeouts(name: &str, value: &JsonValue, base: Timeouts) -> Result<Timeouts, ConfigError> {
+    let mut timeouts = base;
+    let fields = [
+        ("connect", &mut timeouts.connect),
+        ("read", &mut timeouts.read),
+        ("total", &mut timeouts.total),
+    ];
+    for (key, field) in fields {
+        if value[key].is_null() {
+            continue;
+        }
+        let millis = value[key]
+            .as_u64()
+            .ok_or_else(|| invalid(format!("timeouts.{}.{} is not a number", name, key)))?;
+        *field = Duration::from_millis(millis);
+    }
+    Ok(timeouts)
+}
+
+/// `{"group": 123456, "server": "survival", "log": "/srv/mc/logs/latest.log"}`
+fn parse_bridge(
+    value: &JsonValue,
+    rcon: &HashMap<String, RconServer>,
+) -> Result<BridgeConfig, ConfigError> {
+    let group = value["group"]
+        .as_i64()
+        .ok_or_else(|| invalid(String::from("bridges.group is missing")))?;
+    let server = value["server"]
+        .as_str()
+        .ok_or_else(|| invalid(format!("bridges.server of {} is missing", group)))?;
+    if !rcon.contains_key(server) {
+        return Err(invalid(format!("bridge server {} is not in rcon", server)));
+    }
+    let strings = |key: &str| -> Vec<String> {
+        value[key]
+            .members()
+            .filter_map(|item| item.as_str())
+            .map(|item| item.to_string())
+            .collect()
+    };
+    let events = if value["events"].is_null() {
+        BRIDGE_EVENTS
+            .iter()
+            .map(|event| event.to_string())
+            .collect()
+    } else {
+        strings("events")
+    };
+    if let Some(event) = events
+        .iter()
+        .find(|event| !BRIDGE_EVENTS.contains(&event.as_str()))
+    {
+        return Err(invalid(format!("unknown bridge event {}", event)));
+    }
+    Ok(BridgeConfig {
+        group,
+        server: server.to_string(),
+        log: value["log"].as_str().map(|log| log.to_string()),
+        prefix: value["prefix"].as_str().unwrap_or("QQ").to_string(),
+        events,
+        blocked: strings("blocked"),
+    })
+}
+
+impl Config {
+    pub fn parse(data: &str) -> Result<Config, ConfigError> {
+        Config::from_json(&json::parse(data)?)
+    }
+
+    /// 缺省的字段使用默认值
+    pub fn from_json(value: &JsonValue) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+
+        for admin in value["admins"].members() {
+            let admin = admin
+                .as_i64()
+                .ok_or_else(|| invalid(format!("admin {} is not a number", admin)))?;
+            config.admins.push(admin);
+        }
+
+        for (name, server) in value["rcon"].entries() {
+            let field = |key: &str| {
+                server[key]
+                    .as_str()
+                    .map(|value| value.to_string())
+                    .ok_or_else(|| invalid(format!("rcon.{}.{} is missing", name, key)))
+            };
+            config.rcon.insert(
+                name.to_string(),
+                RconServer {
+                    address: field("address")?,
+                    password: field("password")?,
+                },
+            );
+        }
+
+        let default = parse_timeouts(
+            "default",
+            &value["timeouts"]["default"],
+            Timeouts::default(),
+        )?;
+        for (name, timeouts) in value["timeouts"].entries() {
+            config
+                .timeouts
+                .insert(name.to_string(), parse_timeouts(name, timeouts, default)?);
+        }
+
+        if let Some(interval) = value["watch"]["interval"].as_u64() {
+            if interval == 0 {
+                return Err(invalid(String::from("watch.interval must be positive")));
+            }
+            config.watch.interval = Duration::from_secs(interval);
+        }
+        if let Some(threshold) = value["watch"]["offline_threshold"].as_u32() {
+            config.watch.offline_threshold = threshold.max(1);
+        }
+
+        config.font = value["font"].as_str().map(|font| font.to_string());
+
+        for bridge in value["bridges"].members() {
+            let bridge = parse_bridge(bridge, &config.rcon)?;
+            config.bridges.push(bridge);
+        }
+
+        for (group, whitelist) in value["whitelist"].entries() {
+            let group: i64 = group
+                .parse()
+                .map_err(|_| invalid(format!("whitelist group {} is not a number", group)))?;
+            let server = whitelist["server"]
+                .as_str()
+                .ok_or_else(|| invalid(format!("whitelist.{}.server is missing", group)))?;
+            if !config.rcon.contains_key(server) {
+                return Err(invalid(format!(
+                    "whitelist server {} is not in rcon",
+                    server
+                )));
+            }
+            config.whitelist.insert(
+                group,
+                WhitelistConfig {
+                    server: server.to_string(),
+                    auto_remove: whitelist["auto_remove"].as_bool().unwrap_or_default(),
+                },
+            );
+        }
+
+        for (group, welcome) in value["welcome"].entries() {
+            let group: i64 = group
+                .parse()
+                .map_err(|_| invalid(format!("welcome group {} is not a number", group)))?;
+            config.welcome.insert(
+                group,
+                WelcomeConfig {
+                    template: welcome["template"]
+                        .as_str()
+                        .unwrap_or(DEFAULT_WELCOME)
+                        .to_string(),
+                    rules: welcome["rules"].as_str().unwrap_or_default().to_string(),
+                },
+            );
+        }
+
+        for (group, template) in value["video"]["templates"].entries() {
+            let group: i64 = group
+                .parse()
+                .map_err(|_| invalid(format!("video group {} is not a number", group)))?;
+            let template = template
+                .as_str()
+                .ok_or_else(|| invalid(format!("video.templates.{} is not a string", group)))?;
+            config.video.templates.insert(group, template.to_string());
+        }
+        if let Some(ttl) = value["video"]["cache_ttl"].as_u64() {
+            config.video.cache_ttl = Duration::from_secs(ttl);
+        }
+        if let Some(persist) = value["video"]["persist_cache"].as_bool() {
+            config.video.persist_cache = persist;
+        }
+        if let Some(cooldown) = value["video"]["cooldown"].as_u64() {
+            config.video.cooldown = Duration::from_secs(cooldown);
+        }
+
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Config, ConfigError> {
+        Config::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn is_admin(&self, uin: i64) -> bool {
+        self.admins.contains(&uin)
+    }
+
+    pub fn timeouts(&self, command: &str) -> Timeouts {
+        self.timeouts
+            .get(command)
+            .or_else(|| self.timeouts.get("default"))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// 在 main 中调用一次，文件不存在时使用默认配置
+pub fn init(path: &str) -> Result<(), ConfigError> {
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(ConfigError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+            tracing::warn!("{} not found, use default config", path);
+            Config::default()
+        }
+        Err(e) => return Err(e),
+    };
+    CONFIG
+        .set(config)
+        .map_err(|_| invalid(String::from("config is already initialized")))
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let config = Config::parse(
+            r#"{
+                "admins": [10001, 10002],
+                "rcon": {
+                    "survival": {"address": "127.0.0.1:25575", "password": "secret"}
+                },
+                "timeouts": {
+                    "default": {"connect": 3000},
+                    "mcquery": {"read": 1000}
+                },
+                "watch": {"interval": 30},
+                "font": "fonts/unifont.ttf",
+                "bridges": [
+                    {"group": 123456, "server": "survival", "events": ["chat"], "blocked": ["spam"]}
+                ],
+                "whitelist": {"123456": {"server": "survival"}},
+                "welcome": {"123456": {"rules": "禁止破坏地形"}},
+                "video": {"templates": {"123456": "{title}\n{url}"}, "cooldown": 0}
+            }"#,
+        )
+        .unwrap();
+        assert!(config.is_admin(10002));
+        assert!(!config.is_admin(10003));
+        assert_eq!(config.rcon["survival"].address, "127.0.0.1:25575");
+
+        let timeouts = config.timeouts("mcquery");
+        assert_eq!(timeouts.connect, Duration::from_millis(3000));
+        assert_eq!(timeouts.read, Duration::from_millis(1000));
+        assert_eq!(timeouts.total, Timeouts::default().total);
+        assert_eq!(config.timeouts("mcping").read, Timeouts::default().read);
+        assert_eq!(Config::default().timeouts("mcping"), Timeouts::default());
+        assert_eq!(config.watch.interval, Duration::from_secs(30));
+        assert_eq!(config.watch.offline_threshold, 3);
+        assert_eq!(config.font.as_deref(), Some("fonts/unifont.ttf"));
+        assert_eq!(
+            config.bridges,
+            vec![BridgeConfig {
+                group: 123456,
+                server: String::from("survival"),
+                log: None,
+                prefix: String::from("QQ"),
+                events: vec![String::from("chat")],
+                blocked: vec![String::from("spam")],
+            }]
+        );
+        assert_eq!(config.whitelist[&123456].server, "survival");
+        assert!(!config.whitelist[&123456].auto_remove);
+        assert_eq!(config.welcome[&123456].template, DEFAULT_WELCOME);
+        assert_eq!(config.welcome[&123456].rules, "禁止破坏地形");
+        assert_eq!(config.video.template(123456), "{title}\n{url}");
+        assert_eq!(config.video.template(654321), DEFAULT_VIDEO);
+        assert_eq!(config.video.cooldown, Duration::ZERO);
+        assert_eq!(config.video.cache_ttl, VideoConfig::default().cache_ttl);
+        assert!(!config.video.persist_cache);
+
+        assert_eq!(Config::parse("{}").unwrap(), Config::default());
+        assert!(matches!(
+            Config::parse(r#"{"admins": ["10001"]}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            Config::parse(r#"{"rcon": {"survival": {"address": "127.0.0.1"}}}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            Config::parse(r#"{"timeouts": {"mcping": {"read": "5s"}}}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            Config::parse(r#"{"bridges": [{"group": 1, "server": "missing"}]}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            Config::parse(r#"{"whitelist": {"abc": {"server": "survival"}}}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            Config::parse(r#"{"video": {"templates": {"123456": 1}}}"#),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::time::local_time;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// 服务器的历史采样，每个服务器一个文件，每行一条
+// `时间戳,是否可达,在线人数,最大人数,延迟毫秒`
+
+pub const HISTORY_DIR: &str = "history";
+/// 超过这么久的采样会被清理
+pub const RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// unix 时间戳，单位秒
+    pub time: u64,
+    pub reachable: bool,
+    pub online: i64,
+    pub max: i64,
+    pub latency: Option<Duration>,
+}
+
+impl Sample {
+    pub fn unreachable(time: u64) -> Sample {
+        Sample {
+            time,
+            reachable: false,
+            online: 0,
+            max: 0,
+            latency: None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let latency = self
+            .latency
+            .map(|latency| latency.as_millis().to_string())
+            .unwrap_or_default();
+        format!(
+            "{},{},{},{},{}",
+            self.time, self.reachable as u8, self.online, self.max, latency
+        )
+    }
+
+    fn parse_line(line: &str) -> Option<Sample> {
+        let mut fields = line.trim().split(',');
+        let time = fields.next()?.parse().ok()?;
+        let reachable = fields.next()? == "1";
+        let online = fields.next()?.parse().ok()?;
+        let max = fields.next()?.parse().ok()?;
+        let latency = match fields.next()? {
+            "" => None,
+            millis => Some(Duration::from_millis(millis.parse().ok()?)),
+        };
+        Some(Sample {
+            time,
+            reachable,
+            online,
+            max,
+            latency,
+        })
+    }
+}
+
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> HistoryStore {
+        HistoryStore { dir: dir.into() }
+    }
+
+    /// 地址里的 `:`、`[` 等字符不能直接用作文件名，按字节编码成 `%3A` 的形式，
+    /// 这样不同的地址不会对应到同一个文件
+    fn path(&self, address: &str) -> PathBuf {
+        let mut name = String::new();
+        for byte in address.bytes() {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' => name.push(byte as char),
+                _ => name += format!("%{:02X}", byte).as_str(),
+            }
+        }
+        self.dir.join(format!("{}.csv", name))
+    }
+
+    pub fn append(&self, address: &str, sample: &Sample) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(address))?;
+        writeln!(file, "{}", sample.to_line())
+    }
+
+    /// 读取 `since` 之后的采样，没有记录时返回空列表
+    pub fn load(&self, address: &str, since: u64) -> io::Result<Vec<Sample>> {
+        let file = match File::open(self.path(address)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut samples: Vec<Sample> = Vec::new();
+        for line in BufReader::new(file).lines() {
+            match Sample::parse_line(&line?) {
+                Some(sample) if sample.time >= since => samples.push(sample),
+                Some(_) => {}
+                None => tracing::debug!("skip invalid history line of {}", address),
+            }
+        }
+        Ok(samples)
+    }
+
+    /// 删除 `before` 之前的采样
+    pub fn prune(&self, address: &str, before: u64) -> io::Result<()> {
+        let samples = self.load(address, before)?;
+        let data: String = samples
+            .iter()
+            .map(|sample| sample.to_line() + "\n")
+            .collect();
+        let path = self.path(address);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+/// 解析 `24h`、`7d` 这样的时间范围，不能超过保留期限
+pub fn parse_range(range: &str) -> Option<Duration> {
+    let range = range.trim().to_ascii_lowercase();
+    let unit = range.chars().last()?;
+    let value: u64 = range[..range.len() - unit.len_utf8()].parse().ok()?;
+    let duration = match unit {
+        'h' => Duration::from_secs(value.checked_mul(3600)?),
+        'd' => Duration::from_secs(value.checked_mul(24 * 3600)?),
+        _ => return None,
+    };
+    Some(duration).filter(|duration| !duration.is_zero() && *duration <= RETENTION)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub samples: usize,
+    pub reachable: usize,
+    /// 最多在线的人数和时间
+    pub peak: Option<(i64, u64)>,
+    pub average_latency: Option<Duration>,
+    /// 平均在线人数最多的几个小时 `(小时, 平均人数)`，按人数从高到低
+    pub busiest_hours: Vec<(u32, f64)>,
+}
+
+impl Stats {
+    pub fn uptime(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.reachable as f64 / self.samples as f64 * 100.0
+    }
+}
+
+pub fn stats(samples: &[Sample], busiest_hours: usize) -> Stats {
+    let reachable: Vec<&Sample> = samples.iter().filter(|sample| sample.reachable).collect();
+
+    let peak = reachable
+        .iter()
+        .max_by_key(|sample| (sample.online, std::cmp::Reverse(sample.time)))
+        .map(|sample| (sample.online, sample.time));
+
+    let latencies: Vec<Duration> = reachable
+        .iter()
+        .filter_map(|sample| sample.latency)
+        .collect();
+    let average_latency = match latencies.len() {
+        0 => None,
+        len => Some(latencies.iter().sum::<Duration>() / len as u32),
+    };
+
+    let mut hours = [(0i64, 0usize); 24];
+    for sample in &reachable {
+        let (_, _, hour, _) = local_time(sample.time);
+        hours[hour as usize].0 += sample.online;
+        hours[hour as usize].1 += 1;
+    }
+    let mut hours: Vec<(u32, f64)> = hours
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, count))| *count > 0)
+        .map(|(hour, (total, count))| (hour as u32, *total as f64 / *count as f64))
+        .collect();
+    hours.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    hours.truncate(busiest_hours);
+
+    Stats {
+        samples: samples.len(),
+        reachable: reachable.len(),
+        peak,
+        average_latency,
+        busiest_hours: hours,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(time: u64, online: i64, latency: u64) -> Sample {
+        Sample {
+            time,
+            reachable: true,
+            online,
+            max: 20,
+            latency: Some(Duration::from_millis(latency)),
+        }
+    }
+
+    #[test]
+    fn store_test() {
+        let dir = std::env::temp_dir().join(format!("qq-bot-history-{}", std::process::id()));
+        let store = HistoryStore::new(&dir);
+        let address = "[::1]:25565";
+        assert!(store.load(address, 0).unwrap().is_empty());
+
+        store.append(address, &sample(100, 3, 20)).unwrap();
+        store.append(address, &Sample::unreachable(200)).unwrap();
+        store.append(address, &sample(300, 5, 40)).unwrap();
+        assert_eq!(store.load(address, 0).unwrap().len(), 3);
+        assert_eq!(
+            store.load(address, 150).unwrap(),
+            vec![Sample::unreachable(200), sample(300, 5, 40)]
+        );
+
+        store.prune(address, 250).unwrap();
+        assert_eq!(store.load(address, 0).unwrap(), vec![sample(300, 5, 40)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_test() {
+        let store = HistoryStore::new("history");
+        assert_eq!(
+            store.path("mc.example.com:25565"),
+            PathBuf::from("history/mc.example.com%3A25565.csv")
+        );
+        assert_eq!(
+            store.path("[::1]:25565"),
+            PathBuf::from("history/%5B%3A%3A1%5D%3A25565.csv")
+        );
+        assert_ne!(store.path("a.b:25565"), store.path("a_b:25565"));
+        assert_ne!(store.path("a.b:25565"), store.path("a.b_25565"));
+        assert_ne!(store.path("a%3A1"), store.path("a:1"));
+    }
+
+    #[test]
+    fn stats_test() {
+        // 2023-06-01 00:00 北京时间
+        let start = 1685548800;
+        let samples = vec![
+            sample(start, 2, 20),
+            sample(start + 1800, 4, 40),
+            Sample::unreachable(start + 3600),
+            sample(start + 20 * 3600, 10, 60),
+            sample(start + 21 * 3600, 10, 80),
+        ];
+        let stats = stats(&samples, 2);
+        assert_eq!(stats.uptime(), 80.0);
+        assert_eq!(stats.peak, Some((10, start + 20 * 3600)));
+        assert_eq!(stats.average_latency, Some(Duration::from_millis(50)));
+        assert_eq!(stats.busiest_hours, vec![(20, 10.0), (21, 10.0)]);
+
+        let empty = super::stats(&[], 3);
+        assert_eq!(empty.uptime(), 0.0);
+        assert_eq!(empty.peak, None);
+    }
+
+    #[test]
+    fn parse_range_test() {
+        assert_eq!(parse_range("24h"), Some(Duration::from_secs(86400)));
+        assert_eq!(parse_range("7D"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_range("0h"), None);
+        assert_eq!(parse_range("365d"), None);
+        assert_eq!(parse_range("abc"), None);
+        assert_eq!(parse_range("一天"), None);
+        assert_eq!(parse_range(""), None);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod bilibili;
+pub mod bindings;
+pub mod bridge;
+pub mod config;
+pub mod history;
+pub mod mc_protocol;
+pub mod message;
+pub mod render;
+pub mod store;
+pub mod time;
+pub mod timeout;
+pub mod watch;
+pub mod whitelist;
+
 use tracing::Level;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -17,4 +31,4 @@ pub fn init_tracing_subscriber() {
                 .with_target("qq-bot", Level::DEBUG),
         )
         .init();
-}
\ No newline at end of file
+}
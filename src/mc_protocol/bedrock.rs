@@ -0,0 +1,233 @@
+use super::resolve::{lookup_addr, parse_address, ResolvedAddress, Resolver};
+use super::status::PingError;
+use crate::timeout::{timeout, Timeouts};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+// 基岩版通过 RakNet 的 Unconnected Ping/Pong 获取服务器信息
+// 参考 https://wiki.vg/Raknet_Protocol#Unconnected_Ping
+
+pub const DEFAULT_PORT: u16 = 19132;
+
+const UNCONNECTED_PING: u8 = 0x01;
+const UNCONNECTED_PONG: u8 = 0x1c;
+const MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BedrockStatus {
+    pub edition: String,
+    pub motd: String,
+    pub protocol: i32,
+    pub version: String,
+    pub online: i64,
+    pub max: i64,
+    pub server_id: Option<String>,
+    pub sub_motd: Option<String>,
+    pub gamemode: Option<String>,
+    pub port_v4: Option<u16>,
+    pub port_v6: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BedrockResponse {
+    pub address: ResolvedAddress,
+    pub status: BedrockStatus,
+    pub latency: Duration,
+}
+
+/// 解析 Pong 中以分号分隔的服务器信息，例如
+/// `MCPE;Dedicated Server;527;1.19.1;0;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;`
+pub fn parse_server_id(data: &str) -> Result<BedrockStatus, PingError> {
+    let invalid = || PingError::Protocol(format!("invalid bedrock server id: {:?}", data));
+
+    let fields: Vec<&str> = data.split(';').collect();
+    if fields.len() < 6 {
+        return Err(invalid());
+    }
+    let optional = |index: usize| {
+        fields
+            .get(index)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.to_string())
+    };
+
+    Ok(BedrockStatus {
+        edition: fields[0].to_string(),
+        motd: fields[1].to_string(),
+        protocol: fields[2].parse().map_err(|_| invalid())?,
+        version: fields[3].to_string(),
+        online: fields[4].parse().map_err(|_| invalid())?,
+        max: fields[5].parse().map_err(|_| invalid())?,
+        server_id: optional(6),
+        sub_motd: optional(7),
+        gamemode: optional(8),
+        port_v4: optional(10).and_then(|port| port.parse().ok()),
+        port_v6: optional(11).and_then(|port| port.parse().ok()),
+    })
+}
+
+fn ping_packet(time: i64, client_guid: i64) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![UNCONNECTED_PING];
+    buf.extend_from_slice(&time.to_be_bytes());
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&client_guid.to_be_bytes());
+    buf
+}
+
+/// 校验 Pong 并取出其中的服务器信息字符串
+fn parse_pong(buf: &[u8], time: i64) -> Result<String, PingError> {
+    let invalid = |msg: &str| PingError::Protocol(format!("invalid unconnected pong: {}", msg));
+
+    // id(1) + time(8) + server guid(8) + magic(16) + length(2)
+    if buf.len() < 35 {
+        return Err(invalid("packet too short"));
+    }
+    if buf[0] != UNCONNECTED_PONG {
+        return Err(invalid("unexpected packet id"));
+    }
+    if buf[1..9] != time.to_be_bytes() {
+        return Err(invalid("time mismatch"));
+    }
+    if buf[17..33] != MAGIC {
+        return Err(invalid("bad magic"));
+    }
+
+    let length = u16::from_be_bytes([buf[33], buf[34]]) as usize;
+    let data = buf
+        .get(35..35 + length)
+        .ok_or_else(|| invalid("truncated server id"))?;
+    String::from_utf8(data.to_vec()).map_err(|_| invalid("server id is not valid UTF-8"))
+}
+
+pub async fn ping(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<BedrockResponse, PingError> {
+    timeout(timeouts.total, ping_with(resolver, address, timeouts)).await?
+}
+
+async fn ping_with(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<BedrockResponse, PingError> {
+    let (host, port) = parse_address(address)?;
+    let port = port.unwrap_or(DEFAULT_PORT);
+    let addr = lookup_addr(resolver, &host, port).await?;
+
+    let bind_addr: SocketAddr = if addr.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await.map_err(PingError::Connect)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let time = now.as_millis() as i64;
+    let client_guid = now.as_nanos() as i64;
+
+    let start = Instant::now();
+    socket.send(&ping_packet(time, client_guid)).await?;
+    let mut buf = [0u8; 2048];
+    let len = timeout(timeouts.read, socket.recv(&mut buf)).await??;
+    let latency = start.elapsed();
+
+    let status = parse_server_id(&parse_pong(&buf[..len], time)?)?;
+    Ok(BedrockResponse {
+        address: ResolvedAddress {
+            host,
+            port,
+            addr,
+            srv: false,
+        },
+        status,
+        latency,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mc_protocol::resolve::SystemResolver;
+
+    const SERVER_ID: &str =
+        "MCPE;§bGeyser§r Proxy;567;1.19.60;3;100;13253860892328930865;Geyser;Survival;1;19132;19133;";
+
+    fn pong_packet(time: i64, data: &str) -> Vec<u8> {
+        let mut buf: Vec<u8> = vec![UNCONNECTED_PONG];
+        buf.extend_from_slice(&time.to_be_bytes());
+        buf.extend_from_slice(&42i64.to_be_bytes());
+        buf.extend_from_slice(&MAGIC);
+        buf.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        buf.extend_from_slice(data.as_bytes());
+        buf
+    }
+
+    #[test]
+    fn parse_server_id_test() {
+        let status = parse_server_id(SERVER_ID).unwrap();
+        assert_eq!(status.edition, "MCPE");
+        assert_eq!(status.motd, "§bGeyser§r Proxy");
+        assert_eq!(status.protocol, 567);
+        assert_eq!(status.version, "1.19.60");
+        assert_eq!((status.online, status.max), (3, 100));
+        assert_eq!(status.sub_motd.as_deref(), Some("Geyser"));
+        assert_eq!(status.gamemode.as_deref(), Some("Survival"));
+        assert_eq!(status.port_v4, Some(19132));
+        assert_eq!(status.port_v6, Some(19133));
+
+        // 一些旧版本只返回前六个字段
+        let status = parse_server_id("MCPE;Old Server;291;1.7.0;1;20").unwrap();
+        assert_eq!(status.server_id, None);
+        assert_eq!(status.gamemode, None);
+
+        assert!(parse_server_id("MCPE;Broken;abc;1.19;0;10;").is_err());
+        assert!(parse_server_id("MCPE;Short").is_err());
+    }
+
+    #[test]
+    fn parse_pong_test() {
+        let pong = pong_packet(1234, SERVER_ID);
+        assert_eq!(parse_pong(&pong, 1234).unwrap(), SERVER_ID);
+        assert!(parse_pong(&pong, 1235).is_err());
+        assert!(parse_pong(&pong[..40], 1234).is_err());
+
+        let mut bad_magic = pong.clone();
+        bad_magic[18] = 0;
+        assert!(parse_pong(&bad_magic, 1234).is_err());
+    }
+
+    #[test]
+    fn ping_test() {
+        tokio_test::block_on(async {
+            let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let address = server.local_addr().unwrap().to_string();
+            let handle = tokio::spawn(async move {
+                let mut buf = [0u8; 64];
+                let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+                assert_eq!(len, 33);
+                assert_eq!(buf[0], UNCONNECTED_PING);
+                assert_eq!(buf[9..25], MAGIC);
+                let time = i64::from_be_bytes(buf[1..9].try_into().unwrap());
+                server
+                    .send_to(&pong_packet(time, SERVER_ID), peer)
+                    .await
+                    .unwrap();
+            });
+
+            let response = ping(&SystemResolver, &address, &Timeouts::default())
+                .await
+                .unwrap();
+            handle.await.unwrap();
+            assert_eq!(response.status.version, "1.19.60");
+            assert_eq!(response.address.addr.to_string(), address);
+        });
+    }
+}
@@ -0,0 +1,335 @@
+use json::JsonValue;
+
+// 聊天组件 (https://wiki.vg/Chat) 以及旧式 § 格式代码的解析
+
+const NAMED_COLORS: [(&str, [u8; 3]); 16] = [
+    ("black", [0x00, 0x00, 0x00]),
+    ("dark_blue", [0x00, 0x00, 0xAA]),
+    ("dark_green", [0x00, 0xAA, 0x00]),
+    ("dark_aqua", [0x00, 0xAA, 0xAA]),
+    ("dark_red", [0xAA, 0x00, 0x00]),
+    ("dark_purple", [0xAA, 0x00, 0xAA]),
+    ("gold", [0xFF, 0xAA, 0x00]),
+    ("gray", [0xAA, 0xAA, 0xAA]),
+    ("dark_gray", [0x55, 0x55, 0x55]),
+    ("blue", [0x55, 0x55, 0xFF]),
+    ("green", [0x55, 0xFF, 0x55]),
+    ("aqua", [0x55, 0xFF, 0xFF]),
+    ("red", [0xFF, 0x55, 0x55]),
+    ("light_purple", [0xFF, 0x55, 0xFF]),
+    ("yellow", [0xFF, 0xFF, 0x55]),
+    ("white", [0xFF, 0xFF, 0xFF]),
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub color: Option<[u8; 3]>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underlined: bool,
+    pub strikethrough: bool,
+    pub obfuscated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: Style,
+}
+
+pub fn parse_color(name: &str) -> Option<[u8; 3]> {
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(color, _)| *color == name)
+        .map(|(_, rgb)| *rgb)
+}
+
+fn legacy_color(code: char) -> Option<[u8; 3]> {
+    let index = code.to_digit(16)?;
+    Some(NAMED_COLORS[index as usize].1)
+}
+
+/// 把组件树展开成按样式分段的文本
+pub fn parse(component: &JsonValue) -> Vec<TextSpan> {
+    let mut spans: Vec<TextSpan> = Vec::new();
+    parse_component(component, &Style::default(), &mut spans);
+    spans
+}
+
+/// 渲染成去掉所有格式的纯文本，每行首尾的空白(常用于居中)也会去掉
+pub fn to_plain(component: &JsonValue) -> String {
+    let text: String = parse(component).into_iter().map(|span| span.text).collect();
+    text.lines()
+        .map(|line| line.trim())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// 去掉字符串中的 § 格式代码
+pub fn strip_codes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn parse_component(component: &JsonValue, parent: &Style, spans: &mut Vec<TextSpan>) {
+    match component {
+        JsonValue::Short(_) | JsonValue::String(_) => {
+            parse_legacy(component.as_str().unwrap_or_default(), parent, spans);
+        }
+        JsonValue::Number(_) | JsonValue::Boolean(_) => {
+            parse_legacy(&component.to_string(), parent, spans);
+        }
+        // 数组的第一个元素是父组件，其余元素继承它的样式
+        JsonValue::Array(components) => {
+            if let Some((first, rest)) = components.split_first() {
+                let style = apply_style(first, parent);
+                parse_component(first, parent, spans);
+                for child in rest {
+                    parse_component(child, &style, spans);
+                }
+            }
+        }
+        JsonValue::Object(_) => {
+            let style = apply_style(component, parent);
+            if let Some(text) = component["text"].as_str() {
+                parse_legacy(text, &style, spans);
+            } else if let Some(key) = component["translate"].as_str() {
+                let args: Vec<String> = component["with"]
+                    .members()
+                    .map(|arg| {
+                        parse(arg)
+                            .into_iter()
+                            .map(|span| span.text)
+                            .collect::<String>()
+                    })
+                    .collect();
+                let fallback = component["fallback"].as_str().unwrap_or(key);
+                parse_legacy(&translate(fallback, &args), &style, spans);
+            } else if let Some(keybind) = component["keybind"].as_str() {
+                parse_legacy(keybind, &style, spans);
+            } else if let Some(selector) = component["selector"].as_str() {
+                parse_legacy(selector, &style, spans);
+            }
+
+            for child in component["extra"].members() {
+                parse_component(child, &style, spans);
+            }
+        }
+        JsonValue::Null => {}
+    }
+}
+
+fn apply_style(component: &JsonValue, parent: &Style) -> Style {
+    let mut style = parent.clone();
+    if let Some(color) = component["color"].as_str().and_then(parse_color) {
+        style.color = Some(color);
+    }
+    let flags = [
+        ("bold", &mut style.bold),
+        ("italic", &mut style.italic),
+        ("underlined", &mut style.underlined),
+        ("strikethrough", &mut style.strikethrough),
+        ("obfuscated", &mut style.obfuscated),
+    ];
+    for (name, flag) in flags {
+        if let Some(value) = component[name].as_bool() {
+            *flag = value;
+        }
+    }
+    style
+}
+
+/// 替换 `%s`、`%1$s`、`%%` 之类的翻译参数
+fn translate(format: &str, args: &[String]) -> String {
+    let mut result = String::new();
+    let mut next_arg = 0;
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+
+        let mut index = String::new();
+        while let Some(&digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+            index.push(digit);
+            chars.next();
+        }
+        let arg = if index.is_empty() {
+            next_arg += 1;
+            next_arg - 1
+        } else {
+            chars.next_if_eq(&'$');
+            index.parse::<usize>().unwrap_or(1).saturating_sub(1)
+        };
+
+        match chars.next() {
+            Some('%') => result.push('%'),
+            Some('s') | Some('d') => {
+                result += args.get(arg).map(|s| s.as_str()).unwrap_or_default();
+            }
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+fn parse_legacy(text: &str, base: &Style, spans: &mut Vec<TextSpan>) {
+    let mut style = base.clone();
+    let mut current = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '§' {
+            current.push(c);
+            continue;
+        }
+
+        let code = match chars.next() {
+            Some(code) => code.to_ascii_lowercase(),
+            None => break,
+        };
+        if !current.is_empty() {
+            push_span(spans, std::mem::take(&mut current), &style);
+        }
+
+        if let Some(color) = legacy_color(code) {
+            // 颜色代码会重置之前的格式
+            style = Style {
+                color: Some(color),
+                ..Style::default()
+            };
+            continue;
+        }
+        match code {
+            'k' => style.obfuscated = true,
+            'l' => style.bold = true,
+            'm' => style.strikethrough = true,
+            'n' => style.underlined = true,
+            'o' => style.italic = true,
+            'r' => style = Style::default(),
+            _ => {}
+        }
+    }
+
+    if !current.is_empty() {
+        push_span(spans, current, &style);
+    }
+}
+
+fn push_span(spans: &mut Vec<TextSpan>, text: String, style: &Style) {
+    match spans.last_mut() {
+        Some(last) if last.style == *style => last.text += &text,
+        _ => spans.push(TextSpan {
+            text,
+            style: style.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plain(data: &str) -> String {
+        to_plain(&json::parse(data).unwrap())
+    }
+
+    #[test]
+    fn plain_samples_test() {
+        let samples = [
+            // 原版 / Forge 1.12 的纯字符串
+            (r#""A Minecraft Server""#, "A Minecraft Server"),
+            // Paper 配置了 MiniMessage 的 MOTD
+            (
+                r##"{"extra":[{"bold":true,"color":"gold","text":"Survival"},{"color":"gray","text":" | "},{"color":"#55FF55","text":"1.19.4\n"},{"italic":true,"text":"  Welcome!  "}],"text":""}"##,
+                "Survival | 1.19.4\nWelcome!",
+            ),
+            // Velocity 默认 MOTD
+            (
+                r##"{"color":"#09add3","text":"A Velocity Server"}"##,
+                "A Velocity Server",
+            ),
+            // BungeeCord 直接转发带 § 的旧式 MOTD
+            (r#""§1Another Bungee server""#, "Another Bungee server"),
+            (
+                r#"{"extra":[{"color":"dark_aqua","text":"Another "},{"text":"§lBungee§r server"}],"text":""}"#,
+                "Another Bungee server",
+            ),
+            // Forge 1.16 把整段 § 代码放在 text 里
+            (
+                r#"{"text":"§6§lAll The Mods 6 §r§7- §a1.16.5\n          §eModded Survival"}"#,
+                "All The Mods 6 - 1.16.5\nModded Survival",
+            ),
+            // 数组形式以及翻译组件
+            (
+                r#"["", {"text": "Hello, "}, {"translate": "%s and %2$s", "with": ["Steve", {"text": "Alex"}]}]"#,
+                "Hello, Steve and Alex",
+            ),
+        ];
+
+        for (data, expected) in samples {
+            assert_eq!(plain(data), expected, "{}", data);
+        }
+    }
+
+    #[test]
+    fn spans_test() {
+        let spans = parse(
+            &json::parse(r#"{"text":"§cRed §lBold","extra":[{"text":"Blue","color":"blue"}]}"#)
+                .unwrap(),
+        );
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].text, "Red ");
+        assert_eq!(spans[0].style.color, Some([0xFF, 0x55, 0x55]));
+        assert!(!spans[0].style.bold);
+        assert_eq!(spans[1].text, "Bold");
+        assert!(spans[1].style.bold);
+        assert_eq!(spans[2].text, "Blue");
+        assert_eq!(spans[2].style.color, Some([0x55, 0x55, 0xFF]));
+    }
+
+    #[test]
+    fn array_inherit_test() {
+        let spans = parse(&json::parse(r#"[{"text":"a","color":"green"},"b"]"#).unwrap());
+        assert_eq!(
+            spans,
+            vec![TextSpan {
+                text: String::from("ab"),
+                style: Style {
+                    color: Some([0x55, 0xFF, 0x55]),
+                    ..Style::default()
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn strip_codes_test() {
+        assert_eq!(strip_codes("§aGreen §l§nText§r!"), "Green Text!");
+        assert_eq!(strip_codes("trailing §"), "trailing ");
+        assert_eq!(parse_color("#zzzzzz"), None);
+        assert_eq!(parse_color("#fff"), None);
+    }
+}
@@ -0,0 +1,203 @@
+use super::resolve::ResolvedAddress;
+use super::status::{connect, PingError, Players, ServerStatus, Version};
+use crate::timeout::{timeout, Timeouts};
+use json::JsonValue;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// 1.7 之前的服务器列表 ping，参考 https://wiki.vg/Server_List_Ping#1.6
+
+/// 1.6 客户端在 MC|PingHost 中填写的协议号
+const PROTOCOL_1_6: u8 = 74;
+
+fn to_utf16_be(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(|c| c.to_be_bytes()).collect()
+}
+
+/// `0xFE 0x01` 加上 1.6 的 MC|PingHost 插件消息，1.4/1.5 的服务器会忽略后面的部分
+fn legacy_request(host: &str, port: u16) -> Vec<u8> {
+    let channel = "MC|PingHost";
+    let host_data = to_utf16_be(host);
+
+    let mut buf: Vec<u8> = vec![0xFE, 0x01, 0xFA];
+    buf.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&to_utf16_be(channel));
+    buf.extend_from_slice(&(7 + host_data.len() as u16).to_be_bytes());
+    buf.push(PROTOCOL_1_6);
+    buf.extend_from_slice(&(host.encode_utf16().count() as u16).to_be_bytes());
+    buf.extend_from_slice(&host_data);
+    buf.extend_from_slice(&(port as i32).to_be_bytes());
+    buf
+}
+
+/// 解析踢出包里的字符串
+/// 1.4 及以后: `§1\0协议号\0版本\0MOTD\0在线人数\0最大人数`
+/// Beta 1.8 - 1.3: `MOTD§在线人数§最大人数`
+pub fn parse_response(data: &str) -> Result<ServerStatus, PingError> {
+    let invalid = || PingError::Protocol(format!("invalid legacy ping response: {:?}", data));
+
+    let (version, motd, online, max) = match data.strip_prefix("§1\0") {
+        Some(data) => {
+            let fields: Vec<&str> = data.split('\0').collect();
+            if fields.len() != 5 {
+                return Err(invalid());
+            }
+            let version = Version {
+                name: fields[1].to_string(),
+                protocol: fields[0].parse().map_err(|_| invalid())?,
+            };
+            (version, fields[2], fields[3], fields[4])
+        }
+        None => {
+            let mut fields = data.rsplitn(3, '§');
+            let max = fields.next().ok_or_else(invalid)?;
+            let online = fields.next().ok_or_else(invalid)?;
+            let motd = fields.next().ok_or_else(invalid)?;
+            let version = Version {
+                name: String::new(),
+                protocol: -1,
+            };
+            (version, motd, online, max)
+        }
+    };
+
+    Ok(ServerStatus {
+        version,
+        players: Some(Players {
+            max: max.parse().map_err(|_| invalid())?,
+            online: online.parse().map_err(|_| invalid())?,
+            sample: Vec::new(),
+        }),
+        description: JsonValue::from(motd),
+        favicon: None,
+        enforces_secure_chat: None,
+        previews_chat: None,
+        mod_info: None,
+    })
+}
+
+async fn request(
+    address: &ResolvedAddress,
+    data: &[u8],
+    timeouts: &Timeouts,
+) -> Result<(String, Duration), PingError> {
+    let mut stream = connect(address.addr, timeouts).await?;
+
+    let start = Instant::now();
+    stream.write_all(data).await?;
+    stream.flush().await?;
+
+    let packet_id = timeout(timeouts.read, stream.read_u8()).await??;
+    let latency = start.elapsed();
+    if packet_id != 0xFF {
+        return Err(PingError::Protocol(format!(
+            "unexpected legacy packet id {:#04x}",
+            packet_id
+        )));
+    }
+
+    let buf = timeout(timeouts.read, async {
+        let length = stream.read_u16().await? as usize;
+        let mut buf: Vec<u8> = vec![0; length * 2];
+        stream.read_exact(&mut buf).await.map(|_| buf)
+    })
+    .await??;
+    let chars: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    let data = String::from_utf16(&chars)
+        .map_err(|_| PingError::Protocol(String::from("invalid UTF-16 in legacy response")))?;
+    Ok((data, latency))
+}
+
+/// 先用 1.4 - 1.6 的格式，失败后再退回最早只发一个 `0xFE` 的格式
+pub async fn ping(
+    address: &ResolvedAddress,
+    timeouts: &Timeouts,
+) -> Result<(ServerStatus, Duration), PingError> {
+    let request_1_6 = legacy_request(&address.host, address.port);
+    let (data, latency) = match request(address, &request_1_6, timeouts).await {
+        Ok(result) => result,
+        Err(PingError::Timeout) => return Err(PingError::Timeout),
+        Err(e) => {
+            tracing::debug!("legacy ping {} error: {}, retry with 0xFE", address, e);
+            request(address, &[0xFE], timeouts).await?
+        }
+    };
+    Ok((parse_response(&data)?, latency))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mc_protocol::chat;
+    use tokio::net::TcpListener;
+
+    fn kick_packet(data: &str) -> Vec<u8> {
+        let mut buf: Vec<u8> = vec![0xFF];
+        buf.extend_from_slice(&(data.encode_utf16().count() as u16).to_be_bytes());
+        buf.extend_from_slice(&to_utf16_be(data));
+        buf
+    }
+
+    #[test]
+    fn request_test() {
+        let buf = legacy_request("localhost", 25565);
+        assert_eq!(&buf[..5], &[0xFE, 0x01, 0xFA, 0x00, 0x0B]);
+        assert_eq!(&buf[27..29], &[0x00, 7 + 18]);
+        assert_eq!(buf[29], PROTOCOL_1_6);
+        assert_eq!(&buf[30..32], &[0x00, 0x09]);
+        assert_eq!(&buf[buf.len() - 4..], &[0x00, 0x00, 0x63, 0xDD]);
+    }
+
+    #[test]
+    fn parse_response_test() {
+        let status =
+            parse_response("§1\u{0}78\u{0}1.6.4\u{0}§aA Minecraft Server\u{0}3\u{0}20").unwrap();
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.version.protocol, 78);
+        assert_eq!(chat::to_plain(&status.description), "A Minecraft Server");
+        let players = status.players.unwrap();
+        assert_eq!((players.online, players.max), (3, 20));
+
+        let status = parse_response("Beta §lNostalgia§0§20").unwrap();
+        assert_eq!(status.version.protocol, -1);
+        assert_eq!(chat::to_plain(&status.description), "Beta Nostalgia");
+        let players = status.players.unwrap();
+        assert_eq!((players.online, players.max), (0, 20));
+
+        assert!(parse_response("§1\u{0}78\u{0}1.6.4").is_err());
+        assert!(parse_response("no separators").is_err());
+    }
+
+    #[test]
+    fn beta_fallback_test() {
+        tokio_test::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                // 第一次连接直接关闭，模拟不认识 MC|PingHost 的服务器
+                let (stream, _) = listener.accept().await.unwrap();
+                drop(stream);
+
+                let (mut stream, _) = listener.accept().await.unwrap();
+                assert_eq!(stream.read_u8().await.unwrap(), 0xFE);
+                stream
+                    .write_all(&kick_packet("Beta Server§1§10"))
+                    .await
+                    .unwrap();
+            });
+
+            let address = ResolvedAddress {
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                addr,
+                srv: false,
+            };
+            let (status, _) = ping(&address, &Timeouts::default()).await.unwrap();
+            server.await.unwrap();
+            assert_eq!(status.players.unwrap().online, 1);
+        });
+    }
+}
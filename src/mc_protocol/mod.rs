@@ -0,0 +1,329 @@
+pub mod bedrock;
+pub mod chat;
+pub mod legacy;
+pub mod query;
+pub mod rcon;
+pub mod resolve;
+pub mod status;
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// Minecraft Java 版协议的基础类型编解码
+// 参考 https://wiki.vg/Protocol#Data_types
+
+const SEGMENT_BITS: u8 = 0x7F;
+const CONTINUE_BIT: u8 = 0x80;
+
+/// 包长度最多是 3 字节的 VarInt，和原版客户端的限制一致
+pub const MAX_PACKET_LENGTH: i32 = (1 << 21) - 1;
+
+/// 正版玩家名只能是 3 到 16 个字母、数字或下划线
+pub fn is_valid_player_name(name: &str) -> bool {
+    (3..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub fn to_var_int(value: i32) -> Vec<u8> {
+    let mut value = value as u32;
+    let mut result: Vec<u8> = Vec::new();
+    loop {
+        if (value & !(SEGMENT_BITS as u32)) == 0 {
+            result.push(value as u8);
+            return result;
+        }
+
+        result.push((value as u8 & SEGMENT_BITS) | CONTINUE_BIT);
+        value >>= 7;
+    }
+}
+
+pub fn to_var_long(value: i64) -> Vec<u8> {
+    let mut value = value as u64;
+    let mut result: Vec<u8> = Vec::new();
+    loop {
+        if (value & !(SEGMENT_BITS as u64)) == 0 {
+            result.push(value as u8);
+            return result;
+        }
+
+        result.push((value as u8 & SEGMENT_BITS) | CONTINUE_BIT);
+        value >>= 7;
+    }
+}
+
+pub async fn read_var_int<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<i32> {
+    let mut value: i32 = 0;
+    let mut position: u32 = 0;
+
+    loop {
+        let current_byte = reader.read_u8().await?;
+        value |= ((current_byte & SEGMENT_BITS) as i32) << position;
+
+        if (current_byte & CONTINUE_BIT) == 0 {
+            return Ok(value);
+        }
+
+        position += 7;
+        if position >= 32 {
+            return Err(invalid_data("VarInt is too big"));
+        }
+    }
+}
+
+pub async fn read_var_long<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<i64> {
+    let mut value: i64 = 0;
+    let mut position: u32 = 0;
+
+    loop {
+        let current_byte = reader.read_u8().await?;
+        value |= ((current_byte & SEGMENT_BITS) as i64) << position;
+
+        if (current_byte & CONTINUE_BIT) == 0 {
+            return Ok(value);
+        }
+
+        position += 7;
+        if position >= 64 {
+            return Err(invalid_data("VarLong is too big"));
+        }
+    }
+}
+
+pub async fn write_var_int<W: AsyncWrite + Unpin>(writer: &mut W, value: i32) -> io::Result<()> {
+    writer.write_all(&to_var_int(value)).await
+}
+
+pub async fn write_var_long<W: AsyncWrite + Unpin>(writer: &mut W, value: i64) -> io::Result<()> {
+    writer.write_all(&to_var_long(value)).await
+}
+
+pub async fn read_string<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<String> {
+    let length = read_var_int(reader).await?;
+    if length < 0 {
+        return Err(invalid_data("negative string length"));
+    }
+    if length > MAX_PACKET_LENGTH {
+        return Err(invalid_data("string is too long"));
+    }
+
+    let mut result: Vec<u8> = vec![0; length as usize];
+    reader.read_exact(&mut result).await?;
+    String::from_utf8(result).map_err(|_| invalid_data("string is not valid UTF-8"))
+}
+
+pub async fn write_string<W: AsyncWrite + Unpin>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_var_int(writer, value.len() as i32).await?;
+    writer.write_all(value.as_bytes()).await
+}
+
+pub async fn read_unsigned_short<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<u16> {
+    reader.read_u16().await
+}
+
+pub async fn write_unsigned_short<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    value: u16,
+) -> io::Result<()> {
+    writer.write_u16(value).await
+}
+
+pub async fn read_long<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<i64> {
+    reader.read_i64().await
+}
+
+pub async fn write_long<W: AsyncWrite + Unpin>(writer: &mut W, value: i64) -> io::Result<()> {
+    writer.write_i64(value).await
+}
+
+pub async fn read_uuid<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<u128> {
+    reader.read_u128().await
+}
+
+pub async fn write_uuid<W: AsyncWrite + Unpin>(writer: &mut W, value: u128) -> io::Result<()> {
+    writer.write_u128(value).await
+}
+
+/// 打包成 `长度 + 包ID + 数据` 的格式
+pub fn create_packet(packet_id: i32, data: &[u8]) -> Vec<u8> {
+    let pid = to_var_int(packet_id);
+
+    let mut buf = to_var_int((data.len() + pid.len()) as i32);
+    buf.extend_from_slice(&pid);
+    buf.extend_from_slice(data);
+    buf
+}
+
+pub async fn write_packet<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    packet_id: i32,
+    data: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&create_packet(packet_id, data)).await?;
+    writer.flush().await
+}
+
+/// 读取一个完整的数据包，返回包ID和剩余数据
+/// 只读取声明的长度，超过 `MAX_PACKET_LENGTH` 的包直接报错而不分配内存
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(i32, Vec<u8>)> {
+    let length = read_var_int(reader).await?;
+    if length <= 0 {
+        return Err(invalid_data("invalid packet length"));
+    }
+    if length > MAX_PACKET_LENGTH {
+        return Err(invalid_data("packet is too large"));
+    }
+
+    let mut buf: Vec<u8> = vec![0; length as usize];
+    reader.read_exact(&mut buf).await?;
+
+    let mut body = buf.as_slice();
+    let packet_id = read_var_int(&mut body).await?;
+    Ok((packet_id, body.to_vec()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VAR_INTS: [(i32, &[u8]); 10] = [
+        (0, &[0x00]),
+        (1, &[0x01]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (255, &[0xff, 0x01]),
+        (25565, &[0xdd, 0xc7, 0x01]),
+        (2097151, &[0xff, 0xff, 0x7f]),
+        (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        (-2147483648, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+    ];
+
+    const VAR_LONGS: [(i64, &[u8]); 5] = [
+        (0, &[0x00]),
+        (2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (
+            9223372036854775807,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+        ),
+        (
+            -1,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+        ),
+        (
+            -9223372036854775808,
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+        ),
+    ];
+
+    #[test]
+    fn create_packet_test() {
+        let data: Vec<u8> = vec![0x11];
+        let buf = create_packet(0x00, &data);
+
+        assert_eq!(buf, vec![2, 0, 17]);
+        assert_eq!(to_var_int(-1), vec![255, 255, 255, 255, 15]);
+    }
+
+    #[test]
+    fn var_int_test() {
+        for (value, bytes) in VAR_INTS {
+            assert_eq!(to_var_int(value), bytes);
+            let mut reader = bytes;
+            assert_eq!(
+                tokio_test::block_on(read_var_int(&mut reader)).unwrap(),
+                value
+            );
+        }
+
+        let mut too_big: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert!(tokio_test::block_on(read_var_int(&mut too_big)).is_err());
+    }
+
+    #[test]
+    fn var_long_test() {
+        for (value, bytes) in VAR_LONGS {
+            assert_eq!(to_var_long(value), bytes);
+            let mut reader = bytes;
+            assert_eq!(
+                tokio_test::block_on(read_var_long(&mut reader)).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_test() {
+        tokio_test::block_on(async {
+            let mut buf: Vec<u8> = Vec::new();
+            write_string(&mut buf, "mc.example.com").await.unwrap();
+            write_unsigned_short(&mut buf, 25565).await.unwrap();
+            write_long(&mut buf, -42).await.unwrap();
+            write_uuid(&mut buf, 0x069a79f444e94726a5befca90e38aaf5)
+                .await
+                .unwrap();
+
+            let mut reader = buf.as_slice();
+            assert_eq!(read_string(&mut reader).await.unwrap(), "mc.example.com");
+            assert_eq!(read_unsigned_short(&mut reader).await.unwrap(), 25565);
+            assert_eq!(read_long(&mut reader).await.unwrap(), -42);
+            assert_eq!(
+                read_uuid(&mut reader).await.unwrap(),
+                0x069a79f444e94726a5befca90e38aaf5
+            );
+            assert!(reader.is_empty());
+        });
+    }
+
+    #[test]
+    fn packet_test() {
+        tokio_test::block_on(async {
+            let mut buf: Vec<u8> = Vec::new();
+            write_packet(&mut buf, 0x01, &[1, 2, 3]).await.unwrap();
+            write_packet(&mut buf, 0x00, &[]).await.unwrap();
+
+            let mut reader = buf.as_slice();
+            assert_eq!(
+                read_packet(&mut reader).await.unwrap(),
+                (0x01, vec![1, 2, 3])
+            );
+            assert_eq!(read_packet(&mut reader).await.unwrap(), (0x00, vec![]));
+        });
+    }
+
+    #[test]
+    fn invalid_string_test() {
+        let mut reader: &[u8] = &[0x02, 0xc3, 0x28];
+        assert!(tokio_test::block_on(read_string(&mut reader)).is_err());
+
+        // 声明的长度超过剩余数据
+        let mut reader: &[u8] = &[0x05, b'a', b'b'];
+        assert!(tokio_test::block_on(read_string(&mut reader)).is_err());
+
+        let mut reader = to_var_int(i32::MAX);
+        reader.push(b'a');
+        let error = tokio_test::block_on(read_string(&mut reader.as_slice())).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn invalid_packet_test() {
+        tokio_test::block_on(async {
+            let mut reader = to_var_int(MAX_PACKET_LENGTH + 1);
+            reader.push(0x00);
+            let error = read_packet(&mut reader.as_slice()).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+            let mut reader: &[u8] = &[0x00];
+            assert!(read_packet(&mut reader).await.is_err());
+
+            // 数据不足声明的长度
+            let mut reader: &[u8] = &[0x05, 0x00, 0x01];
+            let error = read_packet(&mut reader).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        });
+    }
+}
@@ -0,0 +1,425 @@
+use super::resolve::{lookup_addr, parse_address, ResolvedAddress, Resolver};
+use super::status::PingError;
+use crate::timeout::{timeout, Timeouts};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+// GameSpy4 的 UDP Query 协议，需要服务器开启 enable-query
+// 参考 https://wiki.vg/Query
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const HANDSHAKE: u8 = 0x09;
+const STAT: u8 = 0x00;
+/// full stat 响应中 K/V 段和玩家段前面固定的填充
+const KV_PADDING: &[u8] = b"splitnum\x00\x80\x00";
+const PLAYER_PADDING: &[u8] = b"\x01player_\x00\x00";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicStat {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub online: i64,
+    pub max: i64,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FullStat {
+    pub motd: String,
+    pub game_type: String,
+    pub game_id: String,
+    pub version: String,
+    /// 服务端名称，例如 `Paper on 1.19.4-R0.1-SNAPSHOT`
+    pub software: Option<String>,
+    pub plugins: Vec<String>,
+    pub map: String,
+    pub online: i64,
+    pub max: i64,
+    pub host_port: u16,
+    pub host_ip: String,
+    pub players: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryResponse {
+    pub address: ResolvedAddress,
+    pub stat: FullStat,
+    pub latency: Duration,
+}
+
+fn invalid(msg: &str) -> PingError {
+    PingError::Protocol(format!("invalid query response: {}", msg))
+}
+
+/// 按顺序读取以 `\0` 结尾的字符串
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_string(&mut self) -> Result<String, PingError> {
+        let end = self
+            .buf
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        let text = String::from_utf8_lossy(&self.buf[..end]).to_string();
+        self.buf = &self.buf[end + 1..];
+        Ok(text)
+    }
+
+    fn skip(&mut self, expected: &[u8]) -> Result<(), PingError> {
+        self.buf = self
+            .buf
+            .strip_prefix(expected)
+            .ok_or_else(|| invalid("bad padding"))?;
+        Ok(())
+    }
+
+    fn read_u16_le(&mut self) -> Result<u16, PingError> {
+        if self.buf.len() < 2 {
+            return Err(invalid("packet too short"));
+        }
+        let value = u16::from_le_bytes([self.buf[0], self.buf[1]]);
+        self.buf = &self.buf[2..];
+        Ok(value)
+    }
+}
+
+fn request(packet_type: u8, session_id: i32, payload: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = MAGIC.to_vec();
+    buf.push(packet_type);
+    buf.extend_from_slice(&session_id.to_be_bytes());
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// 校验响应头 (type + session id)，返回剩下的内容
+fn strip_header(buf: &[u8], packet_type: u8, session_id: i32) -> Result<&[u8], PingError> {
+    if buf.len() < 5 {
+        return Err(invalid("packet too short"));
+    }
+    if buf[0] != packet_type {
+        return Err(invalid("unexpected packet type"));
+    }
+    if buf[1..5] != session_id.to_be_bytes() {
+        return Err(invalid("session id mismatch"));
+    }
+    Ok(&buf[5..])
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, PingError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid(&format!("bad {}: {:?}", name, value)))
+}
+
+/// 握手返回的 challenge token 是十进制字符串，发送时转成大端 i32
+fn parse_challenge(payload: &[u8]) -> Result<i32, PingError> {
+    let token = Reader { buf: payload }.read_string()?;
+    parse_number(&token, "challenge token")
+}
+
+fn parse_basic_stat(payload: &[u8]) -> Result<BasicStat, PingError> {
+    let mut reader = Reader { buf: payload };
+    Ok(BasicStat {
+        motd: reader.read_string()?,
+        game_type: reader.read_string()?,
+        map: reader.read_string()?,
+        online: parse_number(&reader.read_string()?, "numplayers")?,
+        max: parse_number(&reader.read_string()?, "maxplayers")?,
+        host_port: reader.read_u16_le()?,
+        host_ip: reader.read_string()?,
+    })
+}
+
+/// 把 `Paper on 1.19.4: WorldEdit 7.2.14; LuckPerms 5.4.66` 拆成服务端和插件列表
+fn parse_plugins(value: &str) -> (Option<String>, Vec<String>) {
+    let (software, plugins) = match value.split_once(':') {
+        Some((software, plugins)) => (software, plugins),
+        None => (value, ""),
+    };
+    let software = Some(software.trim())
+        .filter(|software| !software.is_empty())
+        .map(|software| software.to_string());
+    let plugins = plugins
+        .split(';')
+        .map(|plugin| plugin.trim())
+        .filter(|plugin| !plugin.is_empty())
+        .map(|plugin| plugin.to_string())
+        .collect();
+    (software, plugins)
+}
+
+fn parse_full_stat(payload: &[u8]) -> Result<FullStat, PingError> {
+    let mut reader = Reader { buf: payload };
+    reader.skip(KV_PADDING)?;
+
+    let mut values: HashMap<String, String> = HashMap::new();
+    loop {
+        let key = reader.read_string()?;
+        if key.is_empty() {
+            break;
+        }
+        let value = reader.read_string()?;
+        values.insert(key, value);
+    }
+
+    reader.skip(PLAYER_PADDING)?;
+    let mut players: Vec<String> = Vec::new();
+    loop {
+        let name = reader.read_string()?;
+        if name.is_empty() {
+            break;
+        }
+        players.push(name);
+    }
+
+    let mut take = |key: &str| values.remove(key).unwrap_or_default();
+    let (software, plugins) = parse_plugins(&take("plugins"));
+    Ok(FullStat {
+        motd: take("hostname"),
+        game_type: take("gametype"),
+        game_id: take("game_id"),
+        version: take("version"),
+        software,
+        plugins,
+        map: take("map"),
+        online: parse_number(&take("numplayers"), "numplayers")?,
+        max: parse_number(&take("maxplayers"), "maxplayers")?,
+        host_port: parse_number(&take("hostport"), "hostport")?,
+        host_ip: take("hostip"),
+        players,
+    })
+}
+
+pub struct QueryClient {
+    socket: UdpSocket,
+    session_id: i32,
+    read_timeout: Duration,
+}
+
+impl QueryClient {
+    pub async fn connect(addr: SocketAddr, timeouts: &Timeouts) -> Result<Self, PingError> {
+        let bind_addr: SocketAddr = if addr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        socket.connect(addr).await.map_err(PingError::Connect)?;
+
+        // 服务端只使用每个字节的低 4 位
+        let session_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos() as i32
+            & 0x0F0F0F0F;
+        Ok(QueryClient {
+            socket,
+            session_id,
+            read_timeout: timeouts.read,
+        })
+    }
+
+    async fn send(&self, packet_type: u8, payload: &[u8]) -> Result<Vec<u8>, PingError> {
+        self.socket
+            .send(&request(packet_type, self.session_id, payload))
+            .await?;
+        let mut buf = [0u8; 8192];
+        let len = timeout(self.read_timeout, self.socket.recv(&mut buf)).await??;
+        Ok(strip_header(&buf[..len], packet_type, self.session_id)?.to_vec())
+    }
+
+    pub async fn handshake(&self) -> Result<i32, PingError> {
+        parse_challenge(&self.send(HANDSHAKE, &[]).await?)
+    }
+
+    pub async fn basic_stat(&self, challenge: i32) -> Result<BasicStat, PingError> {
+        parse_basic_stat(&self.send(STAT, &challenge.to_be_bytes()).await?)
+    }
+
+    /// full stat 与 basic stat 的区别只是多了 4 字节的填充
+    pub async fn full_stat(&self, challenge: i32) -> Result<FullStat, PingError> {
+        let mut payload = challenge.to_be_bytes().to_vec();
+        payload.extend_from_slice(&[0; 4]);
+        parse_full_stat(&self.send(STAT, &payload).await?)
+    }
+}
+
+/// Query 端口默认与游戏端口相同，不查 SRV
+pub async fn query(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<QueryResponse, PingError> {
+    timeout(timeouts.total, query_with(resolver, address, timeouts)).await?
+}
+
+/// 直接查询已经解析好的地址，例如状态 ping 通过 SRV 记录找到的地址
+pub async fn query_resolved(
+    address: ResolvedAddress,
+    timeouts: &Timeouts,
+) -> Result<QueryResponse, PingError> {
+    timeout(timeouts.total, query_addr(address, timeouts)).await?
+}
+
+async fn query_with(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<QueryResponse, PingError> {
+    let (host, port) = parse_address(address)?;
+    let port = port.unwrap_or(DEFAULT_PORT);
+    let addr = lookup_addr(resolver, &host, port).await?;
+    query_addr(
+        ResolvedAddress {
+            host,
+            port,
+            addr,
+            srv: false,
+        },
+        timeouts,
+    )
+    .await
+}
+
+async fn query_addr(
+    address: ResolvedAddress,
+    timeouts: &Timeouts,
+) -> Result<QueryResponse, PingError> {
+    let client = QueryClient::connect(address.addr, timeouts).await?;
+    let start = Instant::now();
+    let challenge = client.handshake().await?;
+    let latency = start.elapsed();
+    let stat = client.full_stat(challenge).await?;
+
+    Ok(QueryResponse {
+        address,
+        stat,
+        latency,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mc_protocol::resolve::SystemResolver;
+
+    const BASIC_STAT: &[u8] =
+        b"A Minecraft Server\x00SMP\x00world\x002\x0020\x00\xDD\x63127.0.0.1\x00";
+
+    fn full_stat_payload() -> Vec<u8> {
+        let mut buf = KV_PADDING.to_vec();
+        let values = [
+            ("hostname", "A Minecraft Server"),
+            ("gametype", "SMP"),
+            ("game_id", "MINECRAFT"),
+            ("version", "1.19.4"),
+            (
+                "plugins",
+                "Paper on 1.19.4-R0.1-SNAPSHOT: WorldEdit 7.2.14; LuckPerms 5.4.66",
+            ),
+            ("map", "world"),
+            ("numplayers", "2"),
+            ("maxplayers", "20"),
+            ("hostport", "25565"),
+            ("hostip", "127.0.0.1"),
+        ];
+        for (key, value) in values {
+            buf.extend_from_slice(key.as_bytes());
+            buf.push(0);
+            buf.extend_from_slice(value.as_bytes());
+            buf.push(0);
+        }
+        buf.push(0);
+        buf.extend_from_slice(PLAYER_PADDING);
+        buf.extend_from_slice(b"Steve\x00Alex\x00\x00");
+        buf
+    }
+
+    #[test]
+    fn parse_basic_stat_test() {
+        let stat = parse_basic_stat(BASIC_STAT).unwrap();
+        assert_eq!(stat.motd, "A Minecraft Server");
+        assert_eq!(stat.map, "world");
+        assert_eq!((stat.online, stat.max), (2, 20));
+        assert_eq!(stat.host_port, 25565);
+        assert_eq!(stat.host_ip, "127.0.0.1");
+
+        assert!(parse_basic_stat(&BASIC_STAT[..30]).is_err());
+    }
+
+    #[test]
+    fn parse_full_stat_test() {
+        let stat = parse_full_stat(&full_stat_payload()).unwrap();
+        assert_eq!(stat.version, "1.19.4");
+        assert_eq!(
+            stat.software.as_deref(),
+            Some("Paper on 1.19.4-R0.1-SNAPSHOT")
+        );
+        assert_eq!(stat.plugins, vec!["WorldEdit 7.2.14", "LuckPerms 5.4.66"]);
+        assert_eq!(stat.players, vec!["Steve", "Alex"]);
+        assert_eq!(stat.host_port, 25565);
+
+        assert!(parse_full_stat(&full_stat_payload()[1..]).is_err());
+    }
+
+    #[test]
+    fn parse_plugins_test() {
+        // 原版不返回插件
+        assert_eq!(parse_plugins(""), (None, Vec::new()));
+        assert_eq!(
+            parse_plugins("CraftBukkit on Bukkit 1.2.5-R4.0"),
+            (
+                Some(String::from("CraftBukkit on Bukkit 1.2.5-R4.0")),
+                Vec::new()
+            )
+        );
+    }
+
+    #[test]
+    fn query_test() {
+        tokio_test::block_on(async {
+            let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let address = server.local_addr().unwrap().to_string();
+            let handle = tokio::spawn(async move {
+                let mut buf = [0u8; 64];
+                let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+                assert_eq!(&buf[..3], &[0xFE, 0xFD, HANDSHAKE]);
+                assert_eq!(len, 7);
+                let session = buf[3..7].to_vec();
+
+                let mut response = vec![HANDSHAKE];
+                response.extend_from_slice(&session);
+                response.extend_from_slice(b"9513307\x00");
+                server.send_to(&response, peer).await.unwrap();
+
+                let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+                assert_eq!(len, 15);
+                assert_eq!(buf[2], STAT);
+                assert_eq!(&buf[3..7], &session[..]);
+                assert_eq!(buf[7..11], 9513307i32.to_be_bytes());
+
+                let mut response = vec![STAT];
+                response.extend_from_slice(&session);
+                response.extend_from_slice(&full_stat_payload());
+                server.send_to(&response, peer).await.unwrap();
+            });
+
+            let response = query(&SystemResolver, &address, &Timeouts::default())
+                .await
+                .unwrap();
+            handle.await.unwrap();
+            assert_eq!(response.stat.players.len(), 2);
+            assert_eq!(response.address.addr.to_string(), address);
+        });
+    }
+}
@@ -0,0 +1,366 @@
+use super::resolve::{lookup_addr, parse_address, Resolver};
+use super::status::{connect, PingError};
+use crate::timeout::{timeout, Timeouts};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Source RCON 协议，参考 https://wiki.vg/RCON
+
+pub const DEFAULT_PORT: u16 = 25575;
+
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// 客户端发给服务器的内容最长 1446 字节
+pub const MAX_REQUEST_BODY: usize = 1446;
+/// 服务器按 4096 个字符拆分回复，每个字符 UTF-8 编码后最多 3 字节，
+/// 再加上 id、类型和结尾的两个 0
+const MAX_RESPONSE_LENGTH: i32 = 4096 * 3 + 10;
+
+#[derive(Debug)]
+pub enum RconError {
+    /// 解析地址或建立连接失败
+    Connection(PingError),
+    Timeout,
+    AuthFailed,
+    CommandTooLong(usize),
+    Protocol(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for RconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RconError::Connection(e) => write!(f, "{}", e),
+            RconError::Timeout => write!(f, "timed out"),
+            RconError::AuthFailed => write!(f, "rcon authentication failed"),
+            RconError::CommandTooLong(len) => write!(
+                f,
+                "command is too long ({} bytes, max {})",
+                len, MAX_REQUEST_BODY
+            ),
+            RconError::Protocol(msg) => write!(f, "rcon protocol error: {}", msg),
+            RconError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RconError {}
+
+impl From<io::Error> for RconError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut => RconError::Timeout,
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                RconError::Protocol(e.to_string())
+            }
+            _ => RconError::Io(e),
+        }
+    }
+}
+
+impl From<PingError> for RconError {
+    fn from(e: PingError) -> Self {
+        match e {
+            PingError::Timeout => RconError::Timeout,
+            e => RconError::Connection(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Packet {
+    id: i32,
+    packet_type: i32,
+    /// 一个字符可能被拆在两个包里，拼起来之后再解码
+    body: Vec<u8>,
+}
+
+/// 长度、id、类型都是小端 i32，内容以两个 0 结尾
+async fn write_packet<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    id: i32,
+    packet_type: i32,
+    body: &[u8],
+) -> io::Result<()> {
+    let mut buf: Vec<u8> = Vec::with_capacity(body.len() + 14);
+    buf.extend_from_slice(&(body.len() as i32 + 10).to_le_bytes());
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&packet_type.to_le_bytes());
+    buf.extend_from_slice(body);
+    buf.extend_from_slice(&[0, 0]);
+    writer.write_all(&buf).await?;
+    writer.flush().await
+}
+
+async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Packet, RconError> {
+    let length = reader.read_i32_le().await?;
+    if !(10..=MAX_RESPONSE_LENGTH).contains(&length) {
+        return Err(RconError::Protocol(format!(
+            "invalid packet length {}",
+            length
+        )));
+    }
+    let id = reader.read_i32_le().await?;
+    let packet_type = reader.read_i32_le().await?;
+    let mut body: Vec<u8> = vec![0; length as usize - 8];
+    reader.read_exact(&mut body).await?;
+    if !body.ends_with(&[0, 0]) {
+        return Err(RconError::Protocol(String::from(
+            "packet is not null terminated",
+        )));
+    }
+    body.truncate(body.len() - 2);
+    Ok(Packet {
+        id,
+        packet_type,
+        body,
+    })
+}
+
+pub struct RconClient<S = TcpStream> {
+    stream: S,
+    next_id: i32,
+    read_timeout: Duration,
+}
+
+impl RconClient {
+    /// 连接并登录，地址中没有端口时使用 25575
+    pub async fn connect(
+        resolver: &dyn Resolver,
+        address: &str,
+        password: &str,
+        timeouts: &Timeouts,
+    ) -> Result<RconClient, RconError> {
+        let (host, port) = parse_address(address)?;
+        let addr = lookup_addr(resolver, &host, port.unwrap_or(DEFAULT_PORT)).await?;
+        let stream = connect(addr, timeouts).await?;
+        RconClient::login(stream, password, timeouts).await
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> RconClient<S> {
+    pub async fn login(
+        stream: S,
+        password: &str,
+        timeouts: &Timeouts,
+    ) -> Result<RconClient<S>, RconError> {
+        let mut client = RconClient {
+            stream,
+            next_id: 1,
+            read_timeout: timeouts.read,
+        };
+        let id = client.send(SERVERDATA_AUTH, password).await?;
+        loop {
+            let packet = client.receive().await?;
+            // Source 服务器会先回一个空的 RESPONSE_VALUE，Minecraft 则没有
+            if packet.packet_type != SERVERDATA_AUTH_RESPONSE {
+                continue;
+            }
+            return match packet.id {
+                -1 => Err(RconError::AuthFailed),
+                packet_id if packet_id == id => Ok(client),
+                packet_id => Err(RconError::Protocol(format!(
+                    "unexpected auth response id {}",
+                    packet_id
+                ))),
+            };
+        }
+    }
+
+    async fn send(&mut self, packet_type: i32, body: &str) -> Result<i32, RconError> {
+        if body.len() > MAX_REQUEST_BODY {
+            return Err(RconError::CommandTooLong(body.len()));
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        write_packet(&mut self.stream, id, packet_type, body.as_bytes()).await?;
+        Ok(id)
+    }
+
+    async fn receive(&mut self) -> Result<Packet, RconError> {
+        timeout(self.read_timeout, read_packet(&mut self.stream)).await?
+    }
+
+    /// 执行命令并拼接分成多个包的回复
+    ///
+    /// 回复超过 4096 个字符时会被拆开且没有结束标记，所以在命令之后再发一个无效的包，
+    /// 服务器按顺序处理，收到它的回复就说明命令的回复已经读完了
+    pub async fn command(&mut self, command: &str) -> Result<String, RconError> {
+        let id = self.send(SERVERDATA_EXECCOMMAND, command).await?;
+        let end_id = self.send(SERVERDATA_RESPONSE_VALUE, "").await?;
+
+        let mut result: Vec<u8> = Vec::new();
+        loop {
+            let packet = self.receive().await?;
+            if packet.id == end_id {
+                return Ok(String::from_utf8_lossy(&result).to_string());
+            }
+            if packet.id != id {
+                return Err(RconError::Protocol(format!(
+                    "unexpected response id {}",
+                    packet.id
+                )));
+            }
+            result.extend_from_slice(&packet.body);
+        }
+    }
+}
+
+/// 连接、登录并执行一条命令，整个过程受 `timeouts.total` 限制
+pub async fn execute(
+    resolver: &dyn Resolver,
+    address: &str,
+    password: &str,
+    command: &str,
+    timeouts: &Timeouts,
+) -> Result<String, RconError> {
+    timeout(timeouts.total, async {
+        let mut client = RconClient::connect(resolver, address, password, timeouts).await?;
+        client.command(command).await
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{duplex, DuplexStream};
+
+    /// 模拟 Minecraft 的 RCON：密码为 `secret`，`list` 的回复拆成两个包，
+    /// `say` 的回复把“好”字的三个字节拆在两个包里
+    async fn serve(mut stream: DuplexStream) {
+        let auth = read_packet(&mut stream).await.unwrap();
+        assert_eq!(auth.packet_type, SERVERDATA_AUTH);
+        let id = if auth.body == b"secret" { auth.id } else { -1 };
+        write_packet(&mut stream, id, SERVERDATA_AUTH_RESPONSE, b"")
+            .await
+            .unwrap();
+
+        while let Ok(packet) = read_packet(&mut stream).await {
+            match packet.packet_type {
+                SERVERDATA_EXECCOMMAND => {
+                    let (first, second) = match packet.body.as_slice() {
+                        b"list" => ("x".repeat(4096).into_bytes(), "§aend".as_bytes().to_vec()),
+                        b"say" => {
+                            let body = "你好".as_bytes();
+                            (body[..4].to_vec(), body[4..].to_vec())
+                        }
+                        body => panic!("unexpected command {:?}", body),
+                    };
+                    for body in [first, second] {
+                        write_packet(&mut stream, packet.id, SERVERDATA_RESPONSE_VALUE, &body)
+                            .await
+                            .unwrap();
+                    }
+                }
+                packet_type => {
+                    let body = format!("Unknown request {:x}", packet_type);
+                    write_packet(
+                        &mut stream,
+                        packet.id,
+                        SERVERDATA_RESPONSE_VALUE,
+                        body.as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn command_test() {
+        tokio_test::block_on(async {
+            let (client, server) = duplex(16384);
+            let server = tokio::spawn(serve(server));
+
+            let mut client = RconClient::login(client, "secret", &Timeouts::default())
+                .await
+                .unwrap();
+            let result = client.command("list").await.unwrap();
+            assert_eq!(result.len(), 4096 + "§aend".len());
+            assert!(result.ends_with("§aend"));
+            // 拆开的字符拼起来之后能正常解码
+            assert_eq!(client.command("say").await.unwrap(), "你好");
+
+            assert!(matches!(
+                client.command(&"a".repeat(2000)).await,
+                Err(RconError::CommandTooLong(2000))
+            ));
+            drop(client);
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn auth_failed_test() {
+        tokio_test::block_on(async {
+            let (client, server) = duplex(1024);
+            let server = tokio::spawn(serve(server));
+            assert!(matches!(
+                RconClient::login(client, "wrong", &Timeouts::default()).await,
+                Err(RconError::AuthFailed)
+            ));
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn timeout_test() {
+        tokio_test::block_on(async {
+            // 对方不回复认证结果
+            let (client, _server) = duplex(1024);
+            let timeouts = Timeouts {
+                read: Duration::from_millis(10),
+                ..Timeouts::default()
+            };
+            assert!(matches!(
+                RconClient::login(client, "secret", &timeouts).await,
+                Err(RconError::Timeout)
+            ));
+        });
+    }
+
+    #[test]
+    fn read_packet_test() {
+        tokio_test::block_on(async {
+            let mut buf: Vec<u8> = Vec::new();
+            write_packet(&mut buf, 7, SERVERDATA_RESPONSE_VALUE, b"hello")
+                .await
+                .unwrap();
+            assert_eq!(&buf[..4], &15i32.to_le_bytes());
+            assert_eq!(
+                read_packet(&mut buf.as_slice()).await.unwrap(),
+                Packet {
+                    id: 7,
+                    packet_type: SERVERDATA_RESPONSE_VALUE,
+                    body: b"hello".to_vec(),
+                }
+            );
+
+            // 4096 个中文字符编码后有 12288 字节
+            let chinese = "好".repeat(4096);
+            let mut buf: Vec<u8> = Vec::new();
+            write_packet(&mut buf, 8, SERVERDATA_RESPONSE_VALUE, chinese.as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(
+                read_packet(&mut buf.as_slice()).await.unwrap().body,
+                chinese.as_bytes()
+            );
+
+            let mut bad_length = buf.clone();
+            bad_length[..4].copy_from_slice(&100000i32.to_le_bytes());
+            assert!(matches!(
+                read_packet(&mut bad_length.as_slice()).await,
+                Err(RconError::Protocol(_))
+            ));
+        });
+    }
+}
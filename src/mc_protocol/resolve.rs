@@ -0,0 +1,271 @@
+use super::status::PingError;
+use async_trait::async_trait;
+use dns_lookup::lookup_host;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use trust_dns_resolver::TokioAsyncResolver;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+/// DNS 查询接口，测试时可以换成固定的应答表
+#[async_trait]
+pub trait Resolver: Send + Sync {
+    async fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>>;
+    async fn lookup_ip(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
+/// SRV 走 trust-dns，A/AAAA 仍然交给系统的 getaddrinfo
+pub struct SystemResolver;
+
+#[async_trait]
+impl Resolver for SystemResolver {
+    async fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+        let lookup = resolver.srv_lookup(name).await?;
+        Ok(lookup
+            .iter()
+            .map(|srv| SrvRecord {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_utf8(),
+            })
+            .collect())
+    }
+
+    async fn lookup_ip(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        let host = host.to_string();
+        tokio::task::spawn_blocking(move || lookup_host(&host)).await?
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAddress {
+    pub host: String,
+    pub port: u16,
+    pub addr: SocketAddr,
+    pub srv: bool,
+}
+
+impl fmt::Display for ResolvedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)?;
+        if self.srv {
+            write!(f, " (SRV)")?;
+        }
+        if self.host != self.addr.ip().to_string() {
+            write!(f, " -> {}", self.addr.ip())?;
+        }
+        Ok(())
+    }
+}
+
+/// 拆分 `host[:port]`，支持 `[::1]:25565` 形式的 IPv6 地址
+pub fn parse_address(address: &str) -> Result<(String, Option<u16>), PingError> {
+    let address = address.trim();
+    if address.starts_with('[') && address.ends_with(']') {
+        return Ok((address[1..address.len() - 1].to_string(), None));
+    }
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.starts_with('[') => {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| PingError::InvalidAddress(address.to_string()))?;
+            (
+                host.trim_start_matches('[').trim_end_matches(']'),
+                Some(port),
+            )
+        }
+        _ => (address, None),
+    };
+
+    if host.is_empty() {
+        return Err(PingError::InvalidAddress(address.to_string()));
+    }
+    Ok((host.to_string(), port))
+}
+
+pub(super) async fn lookup_addr(
+    resolver: &dyn Resolver,
+    host: &str,
+    port: u16,
+) -> Result<SocketAddr, PingError> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+
+    let ips = resolver.lookup_ip(host).await.map_err(PingError::Dns)?;
+    match ips.iter().find(|ip| ip.is_ipv4()).or(ips.first()) {
+        Some(&ip) => Ok(SocketAddr::new(ip, port)),
+        None => Err(PingError::Dns(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no address found",
+        ))),
+    }
+}
+
+/// 没有指定端口时先查 `_minecraft._tcp` SRV 记录，查不到再退回 A/AAAA
+pub async fn resolve(
+    resolver: &dyn Resolver,
+    address: &str,
+    default_port: u16,
+) -> Result<ResolvedAddress, PingError> {
+    let (host, port) = parse_address(address)?;
+
+    if port.is_none() && host.parse::<IpAddr>().is_err() {
+        let name = format!("_minecraft._tcp.{}", host);
+        match resolver.lookup_srv(&name).await {
+            Ok(records) => {
+                let record = records
+                    .into_iter()
+                    .min_by_key(|record| (record.priority, u16::MAX - record.weight));
+                if let Some(record) = record {
+                    let target = record.target.trim_end_matches('.').to_string();
+                    let addr = lookup_addr(resolver, &target, record.port).await?;
+                    return Ok(ResolvedAddress {
+                        host: target,
+                        port: record.port,
+                        addr,
+                        srv: true,
+                    });
+                }
+            }
+            Err(e) => tracing::debug!("lookup srv {} error: {}", name, e),
+        }
+    }
+
+    let port = port.unwrap_or(default_port);
+    let addr = lookup_addr(resolver, &host, port).await?;
+    Ok(ResolvedAddress {
+        host,
+        port,
+        addr,
+        srv: false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct StaticResolver {
+        srv: HashMap<&'static str, Vec<SrvRecord>>,
+        ip: HashMap<&'static str, Vec<IpAddr>>,
+    }
+
+    #[async_trait]
+    impl Resolver for StaticResolver {
+        async fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+            self.srv
+                .get(name)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN"))
+        }
+
+        async fn lookup_ip(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+            self.ip
+                .get(host)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN"))
+        }
+    }
+
+    fn resolver() -> StaticResolver {
+        let srv = HashMap::from([(
+            "_minecraft._tcp.example.com",
+            vec![
+                SrvRecord {
+                    priority: 10,
+                    weight: 5,
+                    port: 25570,
+                    target: String::from("backup.example.com."),
+                },
+                SrvRecord {
+                    priority: 0,
+                    weight: 5,
+                    port: 25566,
+                    target: String::from("mc.example.com."),
+                },
+            ],
+        )]);
+        let ip = HashMap::from([
+            (
+                "mc.example.com",
+                vec!["2001:db8::1".parse().unwrap(), "192.0.2.1".parse().unwrap()],
+            ),
+            ("example.com", vec!["192.0.2.2".parse().unwrap()]),
+            ("plain.example.com", vec!["192.0.2.3".parse().unwrap()]),
+        ]);
+        StaticResolver { srv, ip }
+    }
+
+    #[test]
+    fn srv_test() {
+        let resolved = tokio_test::block_on(resolve(&resolver(), "example.com", 25565)).unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedAddress {
+                host: String::from("mc.example.com"),
+                port: 25566,
+                addr: "192.0.2.1:25566".parse().unwrap(),
+                srv: true,
+            }
+        );
+        assert_eq!(
+            resolved.to_string(),
+            "mc.example.com:25566 (SRV) -> 192.0.2.1"
+        );
+    }
+
+    #[test]
+    fn fallback_test() {
+        let resolver = resolver();
+
+        // 指定了端口就不查 SRV
+        let resolved =
+            tokio_test::block_on(resolve(&resolver, "example.com:25565", 25565)).unwrap();
+        assert!(!resolved.srv);
+        assert_eq!(resolved.addr, "192.0.2.2:25565".parse().unwrap());
+
+        let resolved =
+            tokio_test::block_on(resolve(&resolver, "plain.example.com", 25565)).unwrap();
+        assert!(!resolved.srv);
+        assert_eq!(resolved.addr, "192.0.2.3:25565".parse().unwrap());
+
+        let resolved = tokio_test::block_on(resolve(&resolver, "127.0.0.1", 25565)).unwrap();
+        assert_eq!(resolved.to_string(), "127.0.0.1:25565");
+
+        assert!(matches!(
+            tokio_test::block_on(resolve(&resolver, "missing.example.com", 25565)),
+            Err(PingError::Dns(_))
+        ));
+    }
+
+    #[test]
+    fn parse_address_test() {
+        assert_eq!(
+            parse_address("mc.example.com").unwrap(),
+            (String::from("mc.example.com"), None)
+        );
+        assert_eq!(
+            parse_address("mc.example.com:25566").unwrap(),
+            (String::from("mc.example.com"), Some(25566))
+        );
+        assert_eq!(
+            parse_address("[::1]:25567").unwrap(),
+            (String::from("::1"), Some(25567))
+        );
+        assert_eq!(parse_address("::1").unwrap(), (String::from("::1"), None));
+        assert!(parse_address("mc.example.com:abc").is_err());
+        assert!(parse_address(":25565").is_err());
+    }
+}
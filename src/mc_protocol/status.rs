@@ -0,0 +1,565 @@
+use super::legacy;
+use super::resolve::{resolve, ResolvedAddress, Resolver};
+use super::{
+    read_long, read_packet, read_string, write_long, write_packet, write_string,
+    write_unsigned_short, write_var_int,
+};
+use crate::timeout::{timeout, Timeouts};
+use base64::{engine::general_purpose, Engine as _};
+use json::JsonValue;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::net::TcpStream;
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+#[derive(Debug)]
+pub enum PingError {
+    InvalidAddress(String),
+    Dns(io::Error),
+    Connect(io::Error),
+    Timeout,
+    Protocol(String),
+    MalformedJson(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for PingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PingError::InvalidAddress(address) => write!(f, "invalid address: {}", address),
+            PingError::Dns(e) => write!(f, "lookup host error: {}", e),
+            PingError::Connect(e) => write!(f, "connect error: {}", e),
+            PingError::Timeout => write!(f, "timed out"),
+            PingError::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            PingError::MalformedJson(msg) => write!(f, "malformed status json: {}", msg),
+            PingError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PingError {}
+
+impl From<io::Error> for PingError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut => PingError::Timeout,
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                PingError::Protocol(e.to_string())
+            }
+            _ => PingError::Io(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Version {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Players {
+    pub max: i64,
+    pub online: i64,
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModEntry {
+    pub id: String,
+    pub version: String,
+}
+
+/// Forge 服务器附带的 `modinfo`(1.12 及以前) 或 `forgeData`(1.13 及以后)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModInfo {
+    pub loader: String,
+    pub mods: Vec<ModEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub version: Version,
+    pub players: Option<Players>,
+    pub description: JsonValue,
+    pub favicon: Option<String>,
+    pub enforces_secure_chat: Option<bool>,
+    pub previews_chat: Option<bool>,
+    pub mod_info: Option<ModInfo>,
+}
+
+fn malformed(msg: &str) -> PingError {
+    PingError::MalformedJson(msg.to_string())
+}
+
+impl ServerStatus {
+    pub fn parse(data: &str) -> Result<ServerStatus, PingError> {
+        let value = json::parse(data).map_err(|e| PingError::MalformedJson(e.to_string()))?;
+        ServerStatus::from_json(&value)
+    }
+
+    pub fn from_json(value: &JsonValue) -> Result<ServerStatus, PingError> {
+        if !value.is_object() {
+            return Err(malformed("status is not an object"));
+        }
+
+        let version = &value["version"];
+        let version = Version {
+            name: version["name"]
+                .as_str()
+                .ok_or_else(|| malformed("missing version.name"))?
+                .to_string(),
+            protocol: version["protocol"]
+                .as_i32()
+                .ok_or_else(|| malformed("missing version.protocol"))?,
+        };
+
+        let players = &value["players"];
+        let players = if players.is_null() {
+            None
+        } else {
+            let sample = players["sample"]
+                .members()
+                .map(|sample| PlayerSample {
+                    name: sample["name"].as_str().unwrap_or_default().to_string(),
+                    id: sample["id"].as_str().unwrap_or_default().to_string(),
+                })
+                .collect();
+            Some(Players {
+                max: players["max"]
+                    .as_i64()
+                    .ok_or_else(|| malformed("missing players.max"))?,
+                online: players["online"]
+                    .as_i64()
+                    .ok_or_else(|| malformed("missing players.online"))?,
+                sample,
+            })
+        };
+
+        Ok(ServerStatus {
+            version,
+            players,
+            description: value["description"].clone(),
+            favicon: value["favicon"].as_str().map(|s| s.to_string()),
+            enforces_secure_chat: value["enforcesSecureChat"].as_bool(),
+            previews_chat: value["previewsChat"].as_bool(),
+            mod_info: parse_mod_info(value),
+        })
+    }
+
+    /// 解码 `data:image/png;base64,...` 格式的服务器图标
+    pub fn favicon_png(&self) -> Option<Vec<u8>> {
+        let favicon = self.favicon.as_ref()?.replace('\n', "");
+        let index = favicon.find(',')?;
+        general_purpose::STANDARD
+            .decode(&favicon[(index + 1)..])
+            .ok()
+    }
+}
+
+fn parse_mod_info(value: &JsonValue) -> Option<ModInfo> {
+    let modinfo = &value["modinfo"];
+    if modinfo.is_object() {
+        return Some(ModInfo {
+            loader: modinfo["type"].as_str().unwrap_or("FML").to_string(),
+            mods: modinfo["modList"]
+                .members()
+                .map(|entry| ModEntry {
+                    id: entry["modid"].as_str().unwrap_or_default().to_string(),
+                    version: entry["version"].as_str().unwrap_or_default().to_string(),
+                })
+                .collect(),
+        });
+    }
+
+    let forge_data = &value["forgeData"];
+    if forge_data.is_object() {
+        let loader = match forge_data["fmlNetworkVersion"].as_i32() {
+            Some(version) => format!("FML{}", version),
+            None => String::from("FML"),
+        };
+        return Some(ModInfo {
+            loader,
+            mods: forge_data["mods"]
+                .members()
+                .map(|entry| ModEntry {
+                    id: entry["modId"].as_str().unwrap_or_default().to_string(),
+                    version: entry["modmarker"].as_str().unwrap_or_default().to_string(),
+                })
+                .collect(),
+        });
+    }
+
+    None
+}
+
+async fn write_handshake(buffer: &mut Vec<u8>, host: &str, port: u16) -> io::Result<()> {
+    write_var_int(buffer, -1).await?;
+    write_string(buffer, host).await?;
+    write_unsigned_short(buffer, port).await?;
+    write_var_int(buffer, 1).await
+}
+
+#[derive(Debug, Clone)]
+pub struct PingResponse {
+    pub address: ResolvedAddress,
+    pub status: ServerStatus,
+    pub latency: Duration,
+}
+
+pub(super) async fn connect(addr: SocketAddr, timeouts: &Timeouts) -> Result<TcpStream, PingError> {
+    let stream = timeout(timeouts.connect, TcpStream::connect(addr)).await?;
+    stream.map_err(|e| match e.kind() {
+        io::ErrorKind::TimedOut => PingError::Timeout,
+        _ => PingError::Connect(e),
+    })
+}
+
+/// 1.7 以后的 Netty 握手，返回状态和 Ping/Pong 的往返延迟
+async fn ping_modern(
+    address: &ResolvedAddress,
+    timeouts: &Timeouts,
+) -> Result<(ServerStatus, Duration), PingError> {
+    let mut stream = connect(address.addr, timeouts).await?;
+
+    let mut handshake: Vec<u8> = Vec::new();
+    write_handshake(&mut handshake, &address.host, address.port).await?;
+    write_packet(&mut stream, 0x00, &handshake).await?;
+    write_packet(&mut stream, 0x00, &[]).await?;
+
+    let mut reader = BufReader::new(&mut stream);
+    let (packet_id, body) = timeout(timeouts.read, read_packet(&mut reader)).await??;
+    if packet_id != 0x00 {
+        return Err(PingError::Protocol(format!(
+            "unexpected packet id {:#04x}, expected status response",
+            packet_id
+        )));
+    }
+    let data = read_string(&mut body.as_slice()).await?;
+    let status = ServerStatus::parse(&data)?;
+
+    let latency = timeout(timeouts.read, ping_pong(&mut reader)).await??;
+    Ok((status, latency))
+}
+
+/// 新版握手失败时自动退回 1.7 之前的 legacy ping
+pub async fn ping(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<PingResponse, PingError> {
+    timeout(
+        timeouts.total,
+        ping_with_fallback(resolver, address, timeouts),
+    )
+    .await?
+}
+
+async fn ping_with_fallback(
+    resolver: &dyn Resolver,
+    address: &str,
+    timeouts: &Timeouts,
+) -> Result<PingResponse, PingError> {
+    let address = resolve(resolver, address, DEFAULT_PORT).await?;
+
+    let (status, latency) = match ping_modern(&address, timeouts).await {
+        Ok(result) => result,
+        Err(e @ (PingError::Protocol(_) | PingError::Io(_))) => {
+            tracing::debug!("ping {} error: {}, fallback to legacy ping", address, e);
+            match legacy::ping(&address, timeouts).await {
+                Ok(result) => result,
+                Err(legacy_error) => {
+                    tracing::debug!("legacy ping {} error: {}", address, legacy_error);
+                    return Err(e);
+                }
+            }
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(PingResponse {
+        address,
+        status,
+        latency,
+    })
+}
+
+/// 发送 Ping(0x01) 并等待服务器原样返回的 Pong，用来测量往返延迟
+async fn ping_pong<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+) -> Result<Duration, PingError> {
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or_default();
+    let mut data: Vec<u8> = Vec::new();
+    write_long(&mut data, payload).await?;
+
+    let start = Instant::now();
+    write_packet(stream, 0x01, &data).await?;
+    let (packet_id, body) = read_packet(stream).await?;
+    let latency = start.elapsed();
+
+    if packet_id != 0x01 {
+        return Err(PingError::Protocol(format!(
+            "unexpected packet id {:#04x}, expected pong",
+            packet_id
+        )));
+    }
+    if read_long(&mut body.as_slice()).await? != payload {
+        return Err(PingError::Protocol(String::from("pong payload mismatch")));
+    }
+    Ok(latency)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mc_protocol::resolve::SystemResolver;
+    use crate::mc_protocol::{create_packet, to_var_int};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const STATUS: &str = r#"{"version":{"name":"1.19.4","protocol":762},"players":{"max":20,"online":1},"description":"hi"}"#;
+
+    /// 本地的状态服务器，`pong_offset` 不为 0 时返回错误的 Pong
+    async fn serve_status(listener: TcpListener, pong_offset: i64) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let (packet_id, _) = read_packet(&mut stream).await.unwrap();
+        assert_eq!(packet_id, 0x00);
+        assert_eq!(read_packet(&mut stream).await.unwrap(), (0x00, vec![]));
+
+        let mut data: Vec<u8> = Vec::new();
+        write_string(&mut data, STATUS).await.unwrap();
+        write_packet(&mut stream, 0x00, &data).await.unwrap();
+
+        let (packet_id, body) = read_packet(&mut stream).await.unwrap();
+        assert_eq!(packet_id, 0x01);
+        let payload = read_long(&mut body.as_slice()).await.unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        write_long(&mut data, payload + pong_offset).await.unwrap();
+        write_packet(&mut stream, 0x01, &data).await.unwrap();
+    }
+
+    async fn ping_local(pong_offset: i64) -> Result<PingResponse, PingError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(serve_status(listener, pong_offset));
+        let result = ping(&SystemResolver, &address, &Timeouts::default()).await;
+        server.await.unwrap();
+        result
+    }
+
+    #[test]
+    fn legacy_fallback_test() {
+        tokio_test::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let server = tokio::spawn(async move {
+                // 1.6 的服务器不认识新版握手，直接断开
+                let (stream, _) = listener.accept().await.unwrap();
+                drop(stream);
+
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 2];
+                stream.read_exact(&mut request).await.unwrap();
+                assert_eq!(request, [0xFE, 0x01]);
+
+                let data = "§1\u{0}78\u{0}1.6.4\u{0}Modpack\u{0}5\u{0}40";
+                let mut kick: Vec<u8> = vec![0xFF];
+                kick.extend_from_slice(&(data.encode_utf16().count() as u16).to_be_bytes());
+                kick.extend(data.encode_utf16().flat_map(|c| c.to_be_bytes()));
+                stream.write_all(&kick).await.unwrap();
+            });
+
+            let response = ping(&SystemResolver, &address, &Timeouts::default())
+                .await
+                .unwrap();
+            server.await.unwrap();
+            assert_eq!(response.status.version.name, "1.6.4");
+            assert_eq!(response.status.players.unwrap().max, 40);
+        });
+    }
+
+    #[test]
+    fn ping_pong_test() {
+        let response = tokio_test::block_on(ping_local(0)).unwrap();
+        assert_eq!(response.status.version.protocol, 762);
+        assert_eq!(response.address.port, response.address.addr.port());
+        assert!(response.latency < Duration::from_secs(1));
+
+        assert!(matches!(
+            tokio_test::block_on(ping_local(1)),
+            Err(PingError::Protocol(_))
+        ));
+    }
+
+    /// 直接用新版握手请求一个只会回复 `response` 的服务器
+    async fn ping_modern_raw(response: Vec<u8>) -> Result<(ServerStatus, Duration), PingError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            stream.write_all(&response).await.unwrap();
+        });
+
+        let address = ResolvedAddress {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            addr,
+            srv: false,
+        };
+        let result = ping_modern(&address, &Timeouts::default()).await;
+        server.await.unwrap();
+        result
+    }
+
+    #[test]
+    fn framing_test() {
+        tokio_test::block_on(async {
+            let mut data: Vec<u8> = Vec::new();
+            write_string(&mut data, STATUS).await.unwrap();
+            assert!(matches!(
+                ping_modern_raw(create_packet(0x02, &data)).await,
+                Err(PingError::Protocol(_))
+            ));
+
+            // 声明了超大的长度
+            assert!(matches!(
+                ping_modern_raw(vec![0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0x00]).await,
+                Err(PingError::Protocol(_))
+            ));
+
+            // 字符串长度超过包的长度
+            let mut body = vec![0x00];
+            body.extend_from_slice(&to_var_int(100));
+            body.extend_from_slice(b"{}");
+            let mut response = to_var_int(body.len() as i32);
+            response.extend_from_slice(&body);
+            assert!(matches!(
+                ping_modern_raw(response).await,
+                Err(PingError::Protocol(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn timeout_test() {
+        tokio_test::block_on(async {
+            // 接受连接但一直不回复
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                drop(stream);
+            });
+
+            let timeouts = Timeouts {
+                read: Duration::from_millis(20),
+                ..Timeouts::default()
+            };
+            assert!(matches!(
+                ping(&SystemResolver, &address, &timeouts).await,
+                Err(PingError::Timeout)
+            ));
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn parse_vanilla_test() {
+        let status = ServerStatus::parse(
+            r#"{
+                "version": {"name": "1.19.4", "protocol": 762},
+                "players": {
+                    "max": 100,
+                    "online": 2,
+                    "sample": [
+                        {"name": "thinkofdeath", "id": "4566e69f-c907-48ee-8d71-d7ba5aa00d20"},
+                        {"name": "Notch", "id": "069a79f4-44e9-4726-a5be-fca90e38aaf5"}
+                    ]
+                },
+                "description": {"text": "Hello world"},
+                "favicon": "data:image/png;base64,iVBORw0KGgo=",
+                "enforcesSecureChat": true,
+                "previewsChat": false
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(status.version.name, "1.19.4");
+        assert_eq!(status.version.protocol, 762);
+        let players = status.players.as_ref().unwrap();
+        assert_eq!((players.online, players.max), (2, 100));
+        assert_eq!(players.sample[1].name, "Notch");
+        assert_eq!(status.description["text"], "Hello world");
+        assert_eq!(status.enforces_secure_chat, Some(true));
+        assert_eq!(status.previews_chat, Some(false));
+        assert_eq!(
+            status.favicon_png().unwrap(),
+            vec![0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]
+        );
+        assert!(status.mod_info.is_none());
+    }
+
+    #[test]
+    fn parse_forge_test() {
+        let status = ServerStatus::parse(
+            r#"{
+                "version": {"name": "1.12.2", "protocol": 340},
+                "players": {"max": 20, "online": 0},
+                "description": "A Minecraft Server",
+                "modinfo": {"type": "FML", "modList": [{"modid": "minecraft", "version": "1.12.2"}]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status.mod_info,
+            Some(ModInfo {
+                loader: String::from("FML"),
+                mods: vec![ModEntry {
+                    id: String::from("minecraft"),
+                    version: String::from("1.12.2"),
+                }],
+            })
+        );
+
+        let status = ServerStatus::parse(
+            r#"{
+                "version": {"name": "1.16.5", "protocol": 754},
+                "description": {"text": ""},
+                "forgeData": {"fmlNetworkVersion": 2, "mods": [{"modId": "forge", "modmarker": "36.2.39"}]}
+            }"#,
+        )
+        .unwrap();
+        assert!(status.players.is_none());
+        let mod_info = status.mod_info.unwrap();
+        assert_eq!(mod_info.loader, "FML2");
+        assert_eq!(mod_info.mods[0].version, "36.2.39");
+    }
+
+    #[test]
+    fn parse_malformed_test() {
+        assert!(matches!(
+            ServerStatus::parse("not json"),
+            Err(PingError::MalformedJson(_))
+        ));
+        assert!(matches!(
+            ServerStatus::parse(r#"{"players": {"max": 1, "online": 0}}"#),
+            Err(PingError::MalformedJson(_))
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+// 发送到群里的消息的一些辅助函数
+
+/// 按行把长文本拆成多页，每页不超过 `max_lines` 行、`max_chars` 个字符
+/// 单行超过 `max_chars` 时会被截断到下一页
+pub fn paginate(text: &str, max_lines: usize, max_chars: usize) -> Vec<String> {
+    let mut pages: Vec<String> = Vec::new();
+    let mut page = String::new();
+    let mut lines = 0;
+
+    let mut push_line = |line: &str, page: &mut String, lines: &mut usize| {
+        let chars = line.chars().count();
+        if *lines > 0 && (*lines >= max_lines || page.chars().count() + chars + 1 > max_chars) {
+            pages.push(std::mem::take(page));
+            *lines = 0;
+        }
+        if *lines > 0 {
+            page.push('\n');
+        }
+        page.push_str(line);
+        *lines += 1;
+    };
+
+    for line in text.trim_end().lines() {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            push_line("", &mut page, &mut lines);
+        }
+        for chunk in chars.chunks(max_chars.max(1)) {
+            push_line(&chunk.iter().collect::<String>(), &mut page, &mut lines);
+        }
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// 把模板里的 `{name}` 换成 `values` 中对应的值，没有的占位符原样保留
+pub fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result += &rest[..start];
+        let placeholder = &rest[start..];
+        let value = placeholder.find('}').and_then(|end| {
+            let name = &placeholder[1..end];
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                result += value;
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    result + rest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paginate_test() {
+        assert!(paginate("", 10, 100).is_empty());
+        assert_eq!(paginate("a\nb\nc", 10, 100), vec!["a\nb\nc"]);
+        assert_eq!(paginate("a\nb\nc\n", 2, 100), vec!["a\nb", "c"]);
+        assert_eq!(paginate("aaaa\nbb", 10, 6), vec!["aaaa", "bb"]);
+        assert_eq!(paginate("一二三四五", 10, 2), vec!["一二", "三四", "五"]);
+    }
+
+    #[test]
+    fn render_template_test() {
+        let values = [
+            ("nickname", String::from("小明")),
+            ("online", String::from("3")),
+        ];
+        assert_eq!(
+            render_template("欢迎 {nickname}！在线 {online} 人", &values),
+            "欢迎 小明！在线 3 人"
+        );
+        assert_eq!(
+            render_template("{unknown} {nickname}{ {", &values),
+            "{unknown} 小明{ {"
+        );
+        assert_eq!(render_template("{{nickname}}", &values), "{小明}");
+        assert_eq!(render_template("", &values), "");
+    }
+}
@@ -0,0 +1,112 @@
+use proc_qq::{
+    event, module, GroupMessageEvent, MessageChainParseTrait, MessageSendToSourceTrait, Module,
+};
+use qq_bot::bindings::{self, is_valid_alias};
+use qq_bot::config;
+use qq_bot::mc_protocol::resolve::parse_address;
+
+pub fn module() -> Module {
+    module!("bind", "bind", mc_bind, mc_unbind, mc_default, mc_list)
+}
+
+async fn reply(event: &GroupMessageEvent, msg: String) -> anyhow::Result<bool> {
+    event
+        .send_message_to_source(msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
+
+#[event(bot_command = "/mcbind {alias} {host}")]
+async fn mc_bind(event: &GroupMessageEvent, alias: String, host: String) -> anyhow::Result<bool> {
+    if !config::get().is_admin(event.inner.from_uin) {
+        return reply(event, String::from("只有管理员可以绑定服务器")).await;
+    }
+    if !is_valid_alias(&alias) {
+        return reply(
+            event,
+            String::from("别名只能包含字母、数字、- 和 _，最长 32 个字符"),
+        )
+        .await;
+    }
+    if let Err(e) = parse_address(&host) {
+        return reply(event, e.to_string()).await;
+    }
+
+    let result = bindings::global()
+        .lock()
+        .unwrap()
+        .bind(event.inner.group_code, &alias, &host);
+    let msg = match result {
+        Ok(()) => format!("已绑定 {} -> {}", alias, host),
+        Err(e) => {
+            tracing::warn!("bind {} error: {}", alias, e);
+            format!("保存绑定失败：{}", e)
+        }
+    };
+    reply(event, msg).await
+}
+
+#[event(bot_command = "/mcunbind {alias}")]
+async fn mc_unbind(event: &GroupMessageEvent, alias: String) -> anyhow::Result<bool> {
+    if !config::get().is_admin(event.inner.from_uin) {
+        return reply(event, String::from("只有管理员可以解绑服务器")).await;
+    }
+
+    let result = bindings::global()
+        .lock()
+        .unwrap()
+        .unbind(event.inner.group_code, &alias);
+    let msg = match result {
+        Ok(Some(address)) => format!("已解绑 {} ({})", alias, address),
+        Ok(None) => format!("没有绑定 {}", alias),
+        Err(e) => {
+            tracing::warn!("unbind {} error: {}", alias, e);
+            format!("保存绑定失败：{}", e)
+        }
+    };
+    reply(event, msg).await
+}
+
+#[event(bot_command = "/mcdefault {alias}")]
+async fn mc_default(event: &GroupMessageEvent, alias: String) -> anyhow::Result<bool> {
+    if !config::get().is_admin(event.inner.from_uin) {
+        return reply(event, String::from("只有管理员可以修改默认服务器")).await;
+    }
+
+    let result = bindings::global()
+        .lock()
+        .unwrap()
+        .set_default(event.inner.group_code, &alias);
+    let msg = match result {
+        Ok(true) => format!("默认服务器已改为 {}", alias),
+        Ok(false) => format!("没有绑定 {}", alias),
+        Err(e) => {
+            tracing::warn!("set default {} error: {}", alias, e);
+            format!("保存绑定失败：{}", e)
+        }
+    };
+    reply(event, msg).await
+}
+
+#[event(bot_command = "/mclist")]
+async fn mc_list(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let msg = {
+        let bindings = bindings::global().lock().unwrap();
+        match bindings.group(event.inner.group_code) {
+            Some(group) => {
+                let mut msg = String::from("本群绑定的服务器：\n");
+                for (alias, address) in &group.servers {
+                    let mark = if group.default.as_ref() == Some(alias) {
+                        " (默认)"
+                    } else {
+                        ""
+                    };
+                    msg += format!("  {}：{}{}\n", alias, address, mark).as_str();
+                }
+                msg
+            }
+            None => String::from("本群还没有绑定服务器，使用 /mcbind <别名> <地址> 绑定"),
+        }
+    };
+    reply(event, msg).await
+}
@@ -0,0 +1,132 @@
+use proc_qq::{
+    event, module, Client, GroupMessageEvent, MessageChainParseTrait, MessageContentTrait, Module,
+};
+use qq_bot::bridge::{is_blocked, parse_log_line, tellraw_command, LogEvent, LogTail};
+use qq_bot::config::{self, BridgeConfig};
+use qq_bot::mc_protocol::rcon;
+use qq_bot::mc_protocol::resolve::SystemResolver;
+use std::sync::Arc;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn module() -> Module {
+    module!("bridge", "bridge", forward)
+}
+
+/// 把配置了互通的群里的消息转发到游戏，不影响其他命令的处理
+#[event]
+async fn forward(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let config = config::get();
+    let bridges: Vec<&BridgeConfig> = config
+        .bridges
+        .iter()
+        .filter(|bridge| bridge.group == event.inner.group_code)
+        .collect();
+    if bridges.is_empty() {
+        return Ok(false);
+    }
+    // 机器人自己发的消息(包括从游戏转发过来的)不再转发回去
+    if event.inner.from_uin == event.client.uin().await {
+        return Ok(false);
+    }
+    let content = event.message_content();
+    let message = content.trim();
+    // 机器人命令不转发
+    if message.is_empty() || message.starts_with('/') {
+        return Ok(false);
+    }
+
+    let sender = if event.inner.group_card.is_empty() {
+        event.inner.from_uin.to_string()
+    } else {
+        event.inner.group_card.clone()
+    };
+    let timeouts = config.timeouts("bridge");
+    for bridge in bridges {
+        if is_blocked(message, &bridge.blocked) {
+            continue;
+        }
+        let server = &config.rcon[&bridge.server];
+        let command = tellraw_command(&bridge.prefix, &sender, message);
+        if let Err(e) = rcon::execute(
+            &SystemResolver,
+            &server.address,
+            &server.password,
+            &command,
+            &timeouts,
+        )
+        .await
+        {
+            tracing::warn!("forward message to {} error: {}", bridge.server, e);
+        }
+    }
+    Ok(false)
+}
+
+/// 被屏蔽或者是从群里转发过去的消息返回 `None`
+fn format_event(bridge: &BridgeConfig, event: &LogEvent) -> Option<String> {
+    if !bridge.events.iter().any(|kind| kind == event.kind()) {
+        return None;
+    }
+    let msg = match event {
+        LogEvent::Chat { player, message } => {
+            if message.starts_with(&format!("[{}]", bridge.prefix)) {
+                return None;
+            }
+            format!("<{}> {}", player, message)
+        }
+        LogEvent::Join(player) => format!("{} 加入了游戏", player),
+        LogEvent::Leave(player) => format!("{} 离开了游戏", player),
+        LogEvent::Death(message) => message.clone(),
+        LogEvent::Advancement {
+            player,
+            advancement,
+        } => format!("{} 达成了进度 {}", player, advancement),
+    };
+    Some(msg).filter(|msg| !is_blocked(msg, &bridge.blocked))
+}
+
+/// 在 main 里和 run_client 一起启动，读取各个服务器的日志转发到群里
+pub async fn run(client: Arc<Client>) {
+    let mut tails: Vec<(&BridgeConfig, LogTail)> = config::get()
+        .bridges
+        .iter()
+        .filter_map(|bridge| Some((bridge, LogTail::new(bridge.log.as_ref()?))))
+        .collect();
+    if tails.is_empty() {
+        return;
+    }
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        for (bridge, tail) in &mut tails {
+            let lines = match tail.poll() {
+                Ok(lines) => lines,
+                Err(e) => {
+                    // 服务器重启的时候日志文件可能暂时不存在
+                    tracing::debug!("read log of {} error: {}", bridge.server, e);
+                    continue;
+                }
+            };
+            let msg = lines
+                .iter()
+                .filter_map(|line| parse_log_line(line))
+                .filter_map(|event| format_event(bridge, &event))
+                .collect::<Vec<String>>()
+                .join("\n");
+            if msg.is_empty() {
+                continue;
+            }
+            if let Err(e) = client
+                .rq_client
+                .send_group_message(bridge.group, msg.parse_message_chain())
+                .await
+            {
+                tracing::warn!("send bridge message to {} error: {:?}", bridge.group, e);
+            }
+        }
+    }
+}
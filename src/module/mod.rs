@@ -1,8 +1,25 @@
 use proc_qq::Module;
 
+mod bind;
+pub mod bridge;
 mod ping;
+mod rcon;
+mod stats;
 mod video;
+pub mod watch;
+mod welcome;
+mod whitelist;
 
 pub fn get_module() -> Vec<Module> {
-    vec![ping::module(), video::module()]
+    vec![
+        bind::module(),
+        bridge::module(),
+        ping::module(),
+        rcon::module(),
+        stats::module(),
+        video::module(),
+        watch::module(),
+        welcome::module(),
+        whitelist::module(),
+    ]
 }
@@ -0,0 +1,106 @@
+use proc_qq::{
+    event, module, GroupMessageEvent, MessageChainParseTrait, MessageContentTrait,
+    MessageSendToSourceTrait, Module,
+};
+use qq_bot::config;
+use qq_bot::mc_protocol::chat;
+use qq_bot::mc_protocol::rcon::{self, RconError};
+use qq_bot::mc_protocol::resolve::SystemResolver;
+use qq_bot::message::paginate;
+
+const PAGE_LINES: usize = 20;
+const PAGE_CHARS: usize = 1500;
+/// 超过这么多页就只发前面的，避免刷屏
+const MAX_PAGES: usize = 5;
+
+pub fn module() -> Module {
+    module!("rcon", "rcon", rcon)
+}
+
+/// `/rcon <server> <command>`，命令中可以有空格，所以不用 bot_command 解析参数
+#[event]
+async fn rcon(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let content = event.message_content();
+    let args = match content.trim().strip_prefix("/rcon") {
+        Some(args) if args.is_empty() || args.starts_with(char::is_whitespace) => args.trim(),
+        _ => return Ok(false),
+    };
+
+    let config = config::get();
+    if !config.is_admin(event.inner.from_uin) {
+        event
+            .send_message_to_source("只有管理员可以使用 /rcon".parse_message_chain())
+            .await?;
+        return Ok(true);
+    }
+
+    let (name, command) = match args.split_once(char::is_whitespace) {
+        Some((name, command)) if !command.trim().is_empty() => (name, command.trim()),
+        _ => {
+            let mut servers: Vec<&str> = config.rcon.keys().map(|name| name.as_str()).collect();
+            servers.sort();
+            let msg = format!(
+                "用法：/rcon <服务器> <命令>\n可用的服务器：{}",
+                servers.join(", ")
+            );
+            event
+                .send_message_to_source(msg.parse_message_chain())
+                .await?;
+            return Ok(true);
+        }
+    };
+    let server = match config.rcon.get(name) {
+        Some(server) => server,
+        None => {
+            event
+                .send_message_to_source(format!("未知的服务器：{}", name).parse_message_chain())
+                .await?;
+            return Ok(true);
+        }
+    };
+
+    tracing::info!("rcon {} by {}: {}", name, event.inner.from_uin, command);
+    let timeouts = config.timeouts("rcon");
+    let result = rcon::execute(
+        &SystemResolver,
+        &server.address,
+        &server.password,
+        command,
+        &timeouts,
+    )
+    .await;
+    let output = match result {
+        Ok(output) => chat::strip_codes(&output),
+        Err(RconError::Timeout) => format!("请求超时：{} 没有及时响应", name),
+        Err(e) => {
+            tracing::info!("rcon {} error: {}", name, e);
+            e.to_string()
+        }
+    };
+    if output.trim().is_empty() {
+        event
+            .send_message_to_source("命令已执行，没有输出".parse_message_chain())
+            .await?;
+        return Ok(true);
+    }
+
+    let pages = paginate(&output, PAGE_LINES, PAGE_CHARS);
+    let total = pages.len();
+    for (index, page) in pages.into_iter().take(MAX_PAGES).enumerate() {
+        let msg = if total > 1 {
+            format!("{}\n({}/{})", page, index + 1, total)
+        } else {
+            page
+        };
+        event
+            .send_message_to_source(msg.parse_message_chain())
+            .await?;
+    }
+    if total > MAX_PAGES {
+        let msg = format!("输出太长，省略了后面 {} 页", total - MAX_PAGES);
+        event
+            .send_message_to_source(msg.parse_message_chain())
+            .await?;
+    }
+    Ok(true)
+}
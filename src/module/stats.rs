@@ -0,0 +1,175 @@
+use proc_qq::{
+    event, module, GroupMessageEvent, MessageChainAppendTrait, MessageChainParseTrait,
+    MessageContentTrait, MessageSendToSourceTrait, Module,
+};
+use qq_bot::bindings;
+use qq_bot::history::{self, parse_range, HistoryStore, Stats, HISTORY_DIR};
+use qq_bot::render::{self, chart};
+use qq_bot::time::{self, format_time};
+use std::time::Duration;
+
+const DEFAULT_RANGE: Duration = Duration::from_secs(24 * 3600);
+const BUSIEST_HOURS: usize = 3;
+
+pub fn module() -> Module {
+    module!("stats", "stats", mc_stats, mc_chart)
+}
+
+/// 解析 `<命令> [服务器] [范围]`，服务器可以是本群绑定的别名或者地址，省略时使用默认服务器
+/// 返回 `(显示的名字, 地址, 范围)`，参数有误时返回要回复的提示
+pub(crate) fn parse_history_args(
+    event: &GroupMessageEvent,
+    command: &str,
+    args: &[&str],
+) -> Result<(String, String, Duration), String> {
+    let usage = format!("用法：{} [服务器] [24h|7d]", command);
+    let (server, range) = match args {
+        [] => (None, None),
+        [arg] if parse_range(arg).is_some() => (None, Some(*arg)),
+        [server] => (Some(*server), None),
+        [server, range] => (Some(*server), Some(*range)),
+        _ => return Err(usage),
+    };
+    let range = match range {
+        Some(range) => parse_range(range).ok_or(usage)?,
+        None => DEFAULT_RANGE,
+    };
+
+    let bindings = bindings::global().lock().unwrap();
+    match (bindings.lookup(event.inner.group_code, server), server) {
+        (Some((alias, address)), _) => Ok((alias.to_string(), address.to_string(), range)),
+        (None, Some(server)) => Ok((server.to_string(), server.to_string(), range)),
+        (None, None) => Err(String::from(
+            "本群还没有绑定服务器，使用 /mcbind <别名> <地址> 绑定",
+        )),
+    }
+}
+
+/// `24 小时`、`7 天`
+pub(crate) fn format_range(range: Duration) -> String {
+    let hours = range.as_secs() / 3600;
+    if hours % 24 == 0 {
+        format!("{} 天", hours / 24)
+    } else {
+        format!("{} 小时", hours)
+    }
+}
+
+#[event]
+async fn mc_stats(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let content = event.message_content();
+    let args: Vec<&str> = content.split_whitespace().collect();
+    if args.first() != Some(&"/mcstats") {
+        return Ok(false);
+    }
+
+    let msg = match parse_history_args(event, "/mcstats", &args[1..]) {
+        Ok((name, address, range)) => {
+            let since = time::now().saturating_sub(range.as_secs());
+            match HistoryStore::new(HISTORY_DIR).load(&address, since) {
+                Ok(samples) if samples.is_empty() => format!(
+                    "{} 最近 {} 没有记录，只有绑定到群里的服务器才会定时记录",
+                    name,
+                    format_range(range)
+                ),
+                Ok(samples) => format_stats(&name, range, &history::stats(&samples, BUSIEST_HOURS)),
+                Err(e) => {
+                    tracing::warn!("load history of {} error: {}", address, e);
+                    format!("读取记录失败：{}", e)
+                }
+            }
+        }
+        Err(msg) => msg,
+    };
+    event
+        .send_message_to_source(msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
+
+#[event]
+async fn mc_chart(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let content = event.message_content();
+    let args: Vec<&str> = content.split_whitespace().collect();
+    if args.first() != Some(&"/mcchart") {
+        return Ok(false);
+    }
+
+    let (name, address, range) = match parse_history_args(event, "/mcchart", &args[1..]) {
+        Ok(args) => args,
+        Err(msg) => {
+            event
+                .send_message_to_source(msg.parse_message_chain())
+                .await?;
+            return Ok(true);
+        }
+    };
+    let until = time::now();
+    let since = until.saturating_sub(range.as_secs());
+    let msg = match HistoryStore::new(HISTORY_DIR).load(&address, since) {
+        Ok(samples) if samples.is_empty() => format!(
+            "{} 最近 {} 没有记录，只有绑定到群里的服务器才会定时记录",
+            name,
+            format_range(range)
+        ),
+        Ok(samples) => {
+            // 图片里的文字尽量用 ASCII，系统里不一定有中文字体
+            let hours = range.as_secs() / 3600;
+            let title = match hours % 24 {
+                0 => format!("{} - {}d", name, hours / 24),
+                _ => format!("{} - {}h", name, hours),
+            };
+            match chart::render(&title, &samples, since, until, render::font()) {
+                Ok(png) => {
+                    let img = event.upload_image_to_source(png).await?;
+                    event
+                        .send_message_to_source(
+                            format!("{} 最近 {} 的在线人数和延迟：\n", name, format_range(range))
+                                .parse_message_chain()
+                                .append(img),
+                        )
+                        .await?;
+                    return Ok(true);
+                }
+                Err(e) => {
+                    tracing::warn!("render chart of {} error: {}", address, e);
+                    format!("生成图表失败：{}", e)
+                }
+            }
+        }
+        Err(e) => {
+            tracing::warn!("load history of {} error: {}", address, e);
+            format!("读取记录失败：{}", e)
+        }
+    };
+    event
+        .send_message_to_source(msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
+
+fn format_stats(name: &str, range: Duration, stats: &Stats) -> String {
+    let mut result = format!("{} 最近 {} 的统计：\n", name, format_range(range));
+    result += format!(
+        "在线率：{:.1}% ({}/{} 次)\n",
+        stats.uptime(),
+        stats.reachable,
+        stats.samples
+    )
+    .as_str();
+    if let Some((online, time)) = stats.peak {
+        result += format!("最高在线：{} 人 ({})\n", online, format_time(time)).as_str();
+    }
+    if let Some(latency) = stats.average_latency {
+        result += format!("平均延迟：{} ms\n", latency.as_millis()).as_str();
+    }
+    if !stats.busiest_hours.is_empty() {
+        let hours: Vec<String> = stats
+            .busiest_hours
+            .iter()
+            .map(|(hour, average)| format!("{:02}:00 ({:.1} 人)", hour, average))
+            .collect();
+        result += format!("最热闹的时段：{}\n", hours.join("、")).as_str();
+    }
+    result
+}
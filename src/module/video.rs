@@ -1,44 +1,191 @@
+use proc_qq::re_exports::ricq::msg::elem::RQElem;
 use proc_qq::MessageChainAppendTrait;
 use proc_qq::{
     event, module, GroupMessageEvent, MessageChainParseTrait, MessageContentTrait,
     MessageSendToSourceTrait, Module,
 };
+use qq_bot::bilibili::cache;
+use qq_bot::bilibili::link::{
+    card_url, extract_links, extract_short_links, resolve_short_link, VideoLink,
+};
+use qq_bot::bilibili::view::{
+    VideoInfo, ViewError, FORBIDDEN, INVISIBLE, NOT_FOUND, PRIVATE, REVIEWING,
+};
+use qq_bot::config;
+use qq_bot::message::render_template;
+use qq_bot::time;
+
+/// 一条消息里最多预览这么多个视频，避免刷屏
+const MAX_LINKS: usize = 3;
 
 pub fn module() -> Module {
     module!("video", "video", video)
 }
 
+/// 消息文字加上分享卡片里的链接
+fn share_text(event: &GroupMessageEvent) -> String {
+    let mut text = event.message_content();
+    for elem in event.inner.elements.clone() {
+        let url = match elem {
+            RQElem::LightApp(app) => card_url(&app.content),
+            RQElem::RichMsg(msg) => card_url(&msg.template1),
+            _ => None,
+        };
+        if let Some(url) = url {
+            text += "\n";
+            text += url.as_str();
+        }
+    }
+    text
+}
+
 #[event]
 async fn video(event: &GroupMessageEvent) -> anyhow::Result<bool> {
-    let content = event.message_content();
-    match content.find("https://www.bilibili.com/video/") {
-        Some(_) => {
-            let content = &content[31..];
-            let bv = match content.find("/") {
-                Some(index) => &content[..index],
-                None => content,
-            };
-            let result = reqwest::get(format!(
-                "http://api.bilibili.com/x/web-interface/view?bvid={}",
-                bv
-            ))
-            .await?;
-            let json_result = json::parse(result.text().await.unwrap().as_str()).unwrap();
-            let data = &json_result["data"];
-            let title = &data["title"];
-            let pic = data["pic"].to_string();
-
-            let mut msg = format!("https://www.bilibili.com/video/{}\n", bv);
-            msg += title.to_string().as_str();
-            msg += "\n";
-
-            let img = reqwest::get(pic).await?.bytes().await?;
-            let img = event.upload_image_to_source(img).await?;
-            event
-                .send_message_to_source(msg.parse_message_chain().append(img))
-                .await?;
+    let text = share_text(event);
+    let mut links = extract_links(&text);
+    let short_links = extract_short_links(&text);
+    if links.is_empty() && short_links.is_empty() {
+        return Ok(false);
+    }
+
+    let timeouts = config::get().timeouts("video");
+    for url in short_links {
+        match resolve_short_link(&url, &timeouts).await {
+            Ok(resolved) => {
+                for link in resolved {
+                    if !links.contains(&link) {
+                        links.push(link);
+                    }
+                }
+            }
+            Err(e) => tracing::info!("resolve {} error: {}", url, e),
         }
-        None => return Ok(false),
-    };
+    }
+    if links.is_empty() {
+        return Ok(false);
+    }
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.total)
+        .build()?;
+    let (group, cooldown) = (event.inner.group_code, config::get().video.cooldown);
+    for link in links.iter().take(MAX_LINKS) {
+        // 同一个视频刚预览过就不再刷屏
+        if !cache::cooldown()
+            .lock()
+            .unwrap()
+            .reserve(group, &link.bvid, time::now(), cooldown)
+        {
+            continue;
+        }
+        let sent = match fetch_info(&client, &link.bvid).await {
+            Ok(info) => reply_preview(event, &client, link, &info)
+                .await
+                .map(|_| true),
+            Err(e) => {
+                tracing::info!("fetch video {} error: {}", link.bvid, e);
+                // 被删除、没有公开的提示也算预览过，冷却时间内不再重复提示
+                match error_reply(&e, link) {
+                    Some(msg) => event
+                        .send_message_to_source(msg.parse_message_chain())
+                        .await
+                        .map(|_| true)
+                        .map_err(anyhow::Error::from),
+                    None => Ok(false),
+                }
+            }
+        };
+        // 没有发出去的话释放，下次还可以重试
+        if !matches!(sent, Ok(true)) {
+            cache::cooldown().lock().unwrap().release(group, &link.bvid);
+        }
+        sent?;
+    }
     Ok(true)
 }
+
+/// 被删除、没有公开的视频回复一下原因，被风控、网络错误之类的不打扰群里
+fn error_reply(e: &ViewError, link: &VideoLink) -> Option<String> {
+    let reason = match e.code()? {
+        NOT_FOUND | INVISIBLE => "视频不存在或者已经被删除",
+        FORBIDDEN | PRIVATE => "视频没有公开，看不了",
+        REVIEWING => "视频还在审核中",
+        _ => return None,
+    };
+    Some(format!("{}\n{}", link, reason))
+}
+
+/// 先查缓存，没有的话请求接口并缓存起来
+async fn fetch_info(client: &reqwest::Client, bvid: &str) -> Result<VideoInfo, ViewError> {
+    let cached = cache::global()
+        .lock()
+        .unwrap()
+        .info(bvid, time::now())
+        .cloned();
+    if let Some(info) = cached {
+        return Ok(info);
+    }
+    let info = VideoInfo::fetch(client, bvid).await?;
+    let result = cache::global()
+        .lock()
+        .unwrap()
+        .insert_info(bvid, info.clone(), time::now());
+    if let Err(e) = result {
+        tracing::warn!("save video cache error: {}", e);
+    }
+    Ok(info)
+}
+
+async fn fetch_cover(
+    client: &reqwest::Client,
+    bvid: &str,
+    info: &VideoInfo,
+) -> reqwest::Result<Vec<u8>> {
+    let cached = cache::global()
+        .lock()
+        .unwrap()
+        .cover(bvid, time::now())
+        .map(|cover| cover.to_vec());
+    if let Some(cover) = cached {
+        return Ok(cover);
+    }
+    let response = client.get(&info.cover).send().await?.error_for_status()?;
+    let cover = response.bytes().await?.to_vec();
+    let result = cache::global()
+        .lock()
+        .unwrap()
+        .insert_cover(bvid, cover.clone(), time::now());
+    if let Err(e) = result {
+        tracing::warn!("save video cache error: {}", e);
+    }
+    Ok(cover)
+}
+
+async fn reply_preview(
+    event: &GroupMessageEvent,
+    client: &reqwest::Client,
+    link: &VideoLink,
+    info: &VideoInfo,
+) -> anyhow::Result<()> {
+    let template = config::get().video.template(event.inner.group_code);
+    // 空的字段可能留下空行，去掉首尾的空白后再接封面
+    let msg = render_template(template, &info.values(link))
+        .trim()
+        .to_string()
+        + "\n";
+
+    // 封面下载失败时只发文字
+    let chain = match fetch_cover(client, &link.bvid, info).await {
+        Ok(cover) => {
+            let img = event.upload_image_to_source(cover).await?;
+            msg.parse_message_chain().append(img)
+        }
+        Err(e) => {
+            tracing::info!("download cover {} error: {}", info.cover, e);
+            msg.trim_end().parse_message_chain()
+        }
+    };
+    event.send_message_to_source(chain).await?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use proc_qq::{
+    event, module, Client, GroupMessageEvent, MessageChainParseTrait, MessageSendToSourceTrait,
+    Module,
+};
+use qq_bot::bindings;
+use qq_bot::config;
+use qq_bot::history::{HistoryStore, Sample, HISTORY_DIR, RETENTION};
+use qq_bot::mc_protocol::resolve::SystemResolver;
+use qq_bot::time;
+use qq_bot::watch::{format_duration, poll, ServerWatch, WatchEvent};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+/// 同时 ping 的服务器数量，离线的服务器要等到超时，一个个来会拖慢整轮
+const MAX_CONCURRENT_POLLS: usize = 8;
+
+pub fn module() -> Module {
+    module!("watch", "watch", mc_watch)
+}
+
+#[event(bot_command = "/mcwatch {switch}")]
+async fn mc_watch(event: &GroupMessageEvent, switch: String) -> anyhow::Result<bool> {
+    let msg = if !config::get().is_admin(event.inner.from_uin) {
+        String::from("只有管理员可以修改播报设置")
+    } else {
+        let watch = match switch.as_str() {
+            "on" => true,
+            "off" => false,
+            _ => {
+                event
+                    .send_message_to_source("用法：/mcwatch on|off".parse_message_chain())
+                    .await?;
+                return Ok(true);
+            }
+        };
+        let result = bindings::global()
+            .lock()
+            .unwrap()
+            .set_watch(event.inner.group_code, watch);
+        match result {
+            Ok(()) if watch => String::from("已开启本群绑定服务器的玩家进出和离线播报"),
+            Ok(()) => String::from("已关闭播报"),
+            Err(e) => {
+                tracing::warn!("set watch error: {}", e);
+                format!("保存设置失败：{}", e)
+            }
+        }
+    };
+    event
+        .send_message_to_source(msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
+
+fn format_event(alias: &str, event: &WatchEvent) -> String {
+    match event {
+        WatchEvent::Joined(name) => format!("[{}] {} 加入了服务器", alias, name),
+        WatchEvent::Left(name) => format!("[{}] {} 离开了服务器", alias, name),
+        WatchEvent::Offline => format!("[{}] 服务器离线了", alias),
+        WatchEvent::Online { downtime } => {
+            format!(
+                "[{}] 服务器恢复了，离线了 {}",
+                alias,
+                format_duration(*downtime)
+            )
+        }
+    }
+}
+
+/// 在 main 里和 run_client 一起启动，定时 ping 所有绑定的服务器
+/// 每次的结果都记录到历史中，开启了播报的群还会收到玩家进出和离线的消息
+pub async fn run(client: Arc<Client>) {
+    let watch_config = config::get().watch.clone();
+    let timeouts = config::get().timeouts("watch");
+    let store = HistoryStore::new(HISTORY_DIR);
+    let mut watches: HashMap<String, ServerWatch> = HashMap::new();
+    let mut last_prune: Option<Instant> = None;
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_POLLS));
+    let mut interval = tokio::time::interval(watch_config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        let servers = bindings::global().lock().unwrap().servers();
+        watches.retain(|address, _| servers.contains_key(address));
+
+        let prune = last_prune.is_none_or(|time| time.elapsed() >= PRUNE_INTERVAL);
+        if prune {
+            last_prune = Some(Instant::now());
+        }
+
+        // 先并发 ping 完所有服务器，再按顺序记录和播报
+        let mut tasks = JoinSet::new();
+        for address in servers.keys().cloned() {
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let snapshot = poll(&SystemResolver, &address, &timeouts).await;
+                (address, snapshot, time::now(), Instant::now())
+            });
+        }
+        let mut results = HashMap::new();
+        while let Some(result) = tasks.join_next().await {
+            match result {
+                Ok((address, snapshot, now, instant)) => {
+                    results.insert(address, (snapshot, now, instant));
+                }
+                Err(e) => tracing::warn!("poll task error: {}", e),
+            }
+        }
+
+        for (address, bound) in servers {
+            let (snapshot, now, instant) = match results.remove(&address) {
+                Some(result) => result,
+                None => continue,
+            };
+            let sample = match &snapshot {
+                Some(snapshot) => Sample {
+                    time: now,
+                    reachable: true,
+                    online: snapshot.online,
+                    max: snapshot.max,
+                    latency: Some(snapshot.latency),
+                },
+                None => Sample::unreachable(now),
+            };
+            if let Err(e) = store.append(&address, &sample) {
+                tracing::warn!("save history of {} error: {}", address, e);
+            }
+            if prune {
+                let before = now.saturating_sub(RETENTION.as_secs());
+                if let Err(e) = store.prune(&address, before) {
+                    tracing::warn!("prune history of {} error: {}", address, e);
+                }
+            }
+
+            let events = watches.entry(address).or_default().update(
+                snapshot,
+                instant,
+                watch_config.offline_threshold,
+            );
+            if events.is_empty() {
+                continue;
+            }
+
+            for server in bound.iter().filter(|server| server.watch) {
+                let (group, alias) = (server.group, &server.alias);
+                let msg = events
+                    .iter()
+                    .map(|event| format_event(alias, event))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                if let Err(e) = client
+                    .rq_client
+                    .send_group_message(group, msg.parse_message_chain())
+                    .await
+                {
+                    tracing::warn!("send watch message to {} error: {:?}", group, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use proc_qq::re_exports::ricq::client::event::NewMemberEvent;
+use proc_qq::{event, module, MessageChainParseTrait, Module};
+use qq_bot::bindings;
+use qq_bot::config;
+use qq_bot::mc_protocol::resolve::SystemResolver;
+use qq_bot::mc_protocol::status;
+use qq_bot::message::render_template;
+
+pub fn module() -> Module {
+    module!("welcome", "welcome", member_join)
+}
+
+/// 新成员入群时按本群的模板发送欢迎消息，服务器状态现场 ping 一次
+#[event]
+async fn member_join(event: &NewMemberEvent) -> anyhow::Result<bool> {
+    let (group, uin) = (event.inner.group_code, event.inner.member_uin);
+    let welcome = match config::get().welcome.get(&group) {
+        Some(welcome) => welcome,
+        None => return Ok(false),
+    };
+    // 机器人自己被拉进群
+    if uin == event.client.uin().await {
+        return Ok(false);
+    }
+
+    let nickname = match event.client.get_group_member_info(group, uin).await {
+        Ok(info) if !info.nickname.is_empty() => info.nickname,
+        Ok(_) => uin.to_string(),
+        Err(e) => {
+            tracing::info!("get member info of {} error: {:?}", uin, e);
+            uin.to_string()
+        }
+    };
+    let address = bindings::global()
+        .lock()
+        .unwrap()
+        .lookup(group, None)
+        .map(|(_, address)| address.to_string());
+
+    let mut values = vec![
+        ("nickname", nickname),
+        ("uin", uin.to_string()),
+        ("rules", welcome.rules.clone()),
+    ];
+    match &address {
+        Some(address) => {
+            values.push(("server", address.clone()));
+            let timeouts = config::get().timeouts("welcome");
+            match status::ping(&SystemResolver, address, &timeouts).await {
+                Ok(response) => {
+                    let players = response.status.players;
+                    let (online, max) =
+                        players.map_or((0, 0), |players| (players.online, players.max));
+                    values.push(("status", format!("在线 {}/{}", online, max)));
+                    values.push(("online", online.to_string()));
+                    values.push(("max", max.to_string()));
+                    values.push(("version", response.status.version.name));
+                }
+                Err(e) => {
+                    tracing::info!("welcome ping {} error: {}", address, e);
+                    values.push(("status", String::from("暂时连不上")));
+                    values.push(("online", String::from("?")));
+                    values.push(("max", String::from("?")));
+                    values.push(("version", String::from("?")));
+                }
+            }
+        }
+        None => {
+            for key in ["server", "status", "online", "max", "version"] {
+                values.push((key, String::from("未绑定")));
+            }
+        }
+    }
+
+    let msg = render_template(&welcome.template, &values);
+    event
+        .client
+        .send_group_message(group, msg.trim().parse_message_chain())
+        .await?;
+    Ok(true)
+}
@@ -0,0 +1,285 @@
+use proc_qq::re_exports::ricq::client::event::GroupLeaveEvent;
+use proc_qq::{
+    event, module, GroupMessageEvent, MessageChainParseTrait, MessageContentTrait,
+    MessageSendToSourceTrait, Module,
+};
+use qq_bot::config;
+use qq_bot::mc_protocol::rcon::{self, RconError};
+use qq_bot::mc_protocol::resolve::SystemResolver;
+use qq_bot::mc_protocol::{chat, is_valid_player_name};
+use qq_bot::time::{self, format_time};
+use qq_bot::whitelist::{self, ApplyError};
+
+const USAGE: &str = "用法：\n  /whitelist apply <游戏名>\n  /whitelist list\n  /whitelist approve <编号>\n  /whitelist deny <编号>\n  /whitelist remove <游戏名>";
+const INVALID_NAME: &str = "游戏名只能包含 3 到 16 个字母、数字或下划线";
+
+pub fn module() -> Module {
+    module!("whitelist", "whitelist", whitelist, member_leave)
+}
+
+async fn reply(event: &GroupMessageEvent, msg: String) -> anyhow::Result<bool> {
+    event
+        .send_message_to_source(msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
+
+/// 在配置的服务器上执行命令，返回去掉格式代码的输出
+pub(crate) async fn execute(server: &str, command: &str) -> Result<String, RconError> {
+    let config = config::get();
+    let server = &config.rcon[server];
+    let output = rcon::execute(
+        &SystemResolver,
+        &server.address,
+        &server.password,
+        command,
+        &config.timeouts("whitelist"),
+    )
+    .await?;
+    Ok(chat::strip_codes(&output))
+}
+
+#[event]
+async fn whitelist(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+    let content = event.message_content();
+    let args: Vec<&str> = content.split_whitespace().collect();
+    if args.first() != Some(&"/whitelist") {
+        return Ok(false);
+    }
+
+    let config = config::get();
+    let group = event.inner.group_code;
+    let server = match config.whitelist.get(&group) {
+        Some(whitelist) => whitelist.server.as_str(),
+        None => return reply(event, String::from("本群没有开启白名单申请")).await,
+    };
+    let is_admin = config.is_admin(event.inner.from_uin);
+
+    match args[1..] {
+        ["apply", name] => {
+            let result = whitelist::global().lock().unwrap().apply(
+                group,
+                event.inner.from_uin,
+                name,
+                time::now(),
+            );
+            let msg = match result {
+                Ok(id) => format!(
+                    "已提交申请 #{}：{}，请等待管理员审核\n管理员使用 /whitelist approve {} 通过",
+                    id, name, id
+                ),
+                Err(ApplyError::InvalidName) => String::from(INVALID_NAME),
+                Err(ApplyError::Pending(id)) => format!("你已经有一个待审核的申请 #{}", id),
+                Err(ApplyError::Bound(name)) => {
+                    format!("你已经绑定了 {}，需要更换请联系管理员", name)
+                }
+                Err(ApplyError::NameTaken) => format!("{} 已经被申请或绑定了", name),
+                Err(ApplyError::Store(e)) => {
+                    tracing::warn!("save whitelist error: {}", e);
+                    format!("保存申请失败：{}", e)
+                }
+            };
+            reply(event, msg).await
+        }
+        ["list"] => {
+            let msg = {
+                let whitelist = whitelist::global().lock().unwrap();
+                let pending = whitelist.pending(group);
+                if pending.is_empty() {
+                    String::from("没有待审核的申请")
+                } else {
+                    let mut msg = String::from("待审核的申请：\n");
+                    for request in pending {
+                        msg += format!(
+                            "  #{} {} (QQ {}，{})\n",
+                            request.id,
+                            request.name,
+                            request.uin,
+                            format_time(request.time)
+                        )
+                        .as_str();
+                    }
+                    msg
+                }
+            };
+            reply(event, msg).await
+        }
+        ["approve", _] | ["deny", _] | ["remove", _] if !is_admin => {
+            reply(event, String::from("只有管理员可以审核白名单申请")).await
+        }
+        ["approve", id] => {
+            let request = id.parse().ok().and_then(|id| {
+                whitelist::global()
+                    .lock()
+                    .unwrap()
+                    .request(group, id)
+                    .cloned()
+            });
+            let request = match request {
+                Some(request) => request,
+                None => return reply(event, format!("没有编号为 {} 的申请", id)).await,
+            };
+
+            let msg = match execute(server, &format!("whitelist add {}", request.name)).await {
+                // 原版在玩家不存在时也会正常返回，只是输出不同
+                Ok(output) if output.contains("does not exist") => {
+                    format!("添加白名单失败：{}", output)
+                }
+                Ok(output) => match whitelist::global()
+                    .lock()
+                    .unwrap()
+                    .approve(group, request.id)
+                {
+                    Ok(_) => format!(
+                        "已通过申请 #{}，{} 已加入白名单\n{}",
+                        request.id, request.name, output
+                    ),
+                    Err(e) => {
+                        tracing::warn!("save whitelist error: {}", e);
+                        format!("已加入白名单，但是保存绑定失败：{}", e)
+                    }
+                },
+                Err(e) => {
+                    tracing::info!("whitelist add {} error: {}", request.name, e);
+                    format!("添加白名单失败：{}", e)
+                }
+            };
+            reply(event, msg).await
+        }
+        ["deny", id] => {
+            let result = match id.parse() {
+                Ok(id) => whitelist::global().lock().unwrap().deny(group, id),
+                Err(_) => Ok(None),
+            };
+            let msg = match result {
+                Ok(Some(request)) => format!("已拒绝申请 #{} ({})", request.id, request.name),
+                Ok(None) => format!("没有编号为 {} 的申请", id),
+                Err(e) => {
+                    tracing::warn!("save whitelist error: {}", e);
+                    format!("保存失败：{}", e)
+                }
+            };
+            reply(event, msg).await
+        }
+        ["remove", name] => {
+            // 名字会原样拼进 RCON 命令，和申请时一样先检查
+            if !is_valid_player_name(name) {
+                return reply(event, String::from(INVALID_NAME)).await;
+            }
+            let msg = match execute(server, &format!("whitelist remove {}", name)).await {
+                Ok(output) => {
+                    let owner = whitelist::global().lock().unwrap().owner(group, name);
+                    let unlinked = match owner {
+                        Some(uin) => whitelist::global().lock().unwrap().unlink(group, uin),
+                        None => Ok(None),
+                    };
+                    match unlinked {
+                        Ok(Some(_)) => format!(
+                            "{} 已移出白名单，并解除了和 QQ {} 的绑定\n{}",
+                            name,
+                            owner.unwrap_or_default(),
+                            output
+                        ),
+                        Ok(None) => format!("{} 已移出白名单\n{}", name, output),
+                        Err(e) => {
+                            tracing::warn!("save whitelist error: {}", e);
+                            format!("已移出白名单，但是保存绑定失败：{}", e)
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::info!("whitelist remove {} error: {}", name, e);
+                    format!("移出白名单失败：{}", e)
+                }
+            };
+            reply(event, msg).await
+        }
+        _ => reply(event, String::from(USAGE)).await,
+    }
+}
+
+/// 绑定了账号的成员退群或被踢出时，按配置移出白名单或者提醒管理员，并在群里留下记录
+///
+/// 同一个服务器可能给多个群开了白名单，成员还在其中一个群里时保留白名单
+#[event]
+async fn member_leave(event: &GroupLeaveEvent) -> anyhow::Result<bool> {
+    let (group, uin) = (event.inner.group_code, event.inner.member_uin);
+    let config = config::get();
+    let whitelist_config = match config.whitelist.get(&group) {
+        Some(whitelist_config) => whitelist_config,
+        None => return Ok(false),
+    };
+    let name = match whitelist::global().lock().unwrap().account(group, uin) {
+        Some(name) => name.to_string(),
+        None => return Ok(false),
+    };
+
+    let reason = match event.inner.operator_uin {
+        Some(operator) if operator != uin => format!("被 {} 移出了群", operator),
+        _ => String::from("退出了群"),
+    };
+    let mut msg = format!(
+        "[白名单] QQ {} {}，绑定的游戏账号是 {}\n",
+        uin, reason, name
+    );
+
+    // 查不到其它群的成员信息时不确定还在不在，不自动移出
+    let (mut remaining, mut unknown) = (None, false);
+    let others = config.whitelist.iter().filter(|(other, other_config)| {
+        **other != group && other_config.server == whitelist_config.server
+    });
+    for (other, _) in others {
+        match event.client.get_group_member_info(*other, uin).await {
+            Ok(info) if info.uin == uin => {
+                remaining = Some(*other);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::info!("get member info of {} in {} error: {:?}", uin, other, e);
+                unknown = true;
+            }
+        }
+    }
+
+    if let Some(other) = remaining {
+        msg += format!("还在群 {} 中，保留白名单", other).as_str();
+    } else if whitelist_config.auto_remove && unknown {
+        msg += format!(
+            "无法确认是否还在其它群中，没有自动移出\n请管理员确认后使用 /whitelist remove {} 移出白名单",
+            name
+        )
+        .as_str();
+    } else if whitelist_config.auto_remove {
+        match execute(
+            &whitelist_config.server,
+            &format!("whitelist remove {}", name),
+        )
+        .await
+        {
+            Ok(_) => {
+                if let Err(e) = whitelist::global().lock().unwrap().unlink(group, uin) {
+                    tracing::warn!("save whitelist error: {}", e);
+                }
+                tracing::info!("removed {} of {} from whitelist", name, uin);
+                msg += "已自动移出白名单";
+            }
+            Err(e) => {
+                tracing::warn!("whitelist remove {} error: {}", name, e);
+                msg += format!(
+                    "自动移出白名单失败：{}\n请管理员使用 /whitelist remove {} 处理",
+                    e, name
+                )
+                .as_str();
+            }
+        }
+    } else {
+        msg += format!("请管理员确认后使用 /whitelist remove {} 移出白名单", name).as_str();
+    }
+
+    event
+        .client
+        .send_group_message(group, msg.parse_message_chain())
+        .await?;
+    Ok(true)
+}
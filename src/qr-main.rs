@@ -3,7 +3,7 @@ use proc_qq::*;
 use std::sync::Arc;
 
 mod module;
-use qq_bot::init_tracing_subscriber;
+use qq_bot::{config, init_tracing_subscriber};
 
 #[result]
 pub async fn on_result(result: &EventResult) -> anyhow::Result<bool> {
@@ -28,6 +28,7 @@ async fn main() {
     let modules = module::get_module();
 
     init_tracing_subscriber();
+    config::init(config::CONFIG_PATH).unwrap();
     let client = ClientBuilder::new()
         .authentication(Authentication::QRCode)
         .show_rq(ShowQR::OpenBySystem)
@@ -39,5 +40,8 @@ async fn main() {
         .build()
         .await
         .unwrap();
-    run_client(Arc::new(client)).await.unwrap();
+    let client = Arc::new(client);
+    tokio::spawn(module::watch::run(client.clone()));
+    tokio::spawn(module::bridge::run(client.clone()));
+    run_client(client).await.unwrap();
 }
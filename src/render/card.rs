@@ -0,0 +1,293 @@
+use super::{draw_text, encode_png, text_width};
+use crate::mc_protocol::chat::{self, TextSpan};
+use crate::mc_protocol::status::ServerStatus;
+use image::imageops::FilterType;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+use rusttype::Font;
+use std::time::Duration;
+
+// 仿照游戏里多人游戏列表的一项：左边是图标，右边是名字、MOTD、版本、人数和信号格
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 88;
+const ICON_SIZE: u32 = 64;
+const ICON: i32 = 12;
+const TEXT_LEFT: i32 = 88;
+const TEXT_RIGHT: i32 = WIDTH as i32 - 10;
+const NAME_SIZE: f32 = 18.0;
+const TEXT_SIZE: f32 = 16.0;
+const MOTD_LINES: usize = 2;
+
+const BACKGROUND: [u8; 3] = [32, 32, 32];
+const BORDER: [u8; 3] = [128, 128, 128];
+const ICON_PLACEHOLDER: [u8; 3] = [64, 64, 64];
+const WHITE: [u8; 3] = [0xFF, 0xFF, 0xFF];
+const GRAY: [u8; 3] = [0xAA, 0xAA, 0xAA];
+const DARK_GRAY: [u8; 3] = [0x55, 0x55, 0x55];
+const SIGNAL: [u8; 3] = [0x55, 0xFF, 0x55];
+
+/// 和游戏里一样按延迟显示 1 到 5 格信号
+pub fn signal_bars(latency: Duration) -> u32 {
+    match latency.as_millis() {
+        0..=149 => 5,
+        150..=299 => 4,
+        300..=599 => 3,
+        600..=999 => 2,
+        _ => 1,
+    }
+}
+
+/// 按换行把 MOTD 分成最多 `MOTD_LINES` 行
+pub fn motd_lines(spans: Vec<TextSpan>) -> Vec<Vec<TextSpan>> {
+    let mut lines: Vec<Vec<TextSpan>> = vec![Vec::new()];
+    for span in spans {
+        for (index, text) in span.text.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(Vec::new());
+            }
+            if !text.is_empty() {
+                lines.last_mut().unwrap().push(TextSpan {
+                    text: text.to_string(),
+                    style: span.style.clone(),
+                });
+            }
+        }
+    }
+    lines.truncate(MOTD_LINES);
+    lines
+}
+
+/// 去掉放不下的字符
+fn fit<'a>(font: Option<&Font>, size: f32, text: &'a str, width: i32) -> &'a str {
+    let mut end = text.len();
+    while end > 0 && text_width(font, size, &text[..end]) > width {
+        end = text[..end]
+            .char_indices()
+            .last()
+            .map_or(0, |(index, _)| index);
+    }
+    &text[..end]
+}
+
+fn draw_span(
+    image: &mut RgbImage,
+    font: Option<&Font>,
+    span: &TextSpan,
+    (x, y): (i32, i32),
+) -> i32 {
+    let text = fit(font, TEXT_SIZE, &span.text, TEXT_RIGHT - x);
+    let color = span.style.color.unwrap_or(GRAY);
+    let width = draw_text(image, font, color, (x, y), TEXT_SIZE, text);
+    if span.style.bold {
+        draw_text(image, font, color, (x + 1, y), TEXT_SIZE, text);
+    }
+    if width > 0 && span.style.underlined {
+        let rect = Rect::at(x, y + TEXT_SIZE as i32 + 1).of_size(width as u32, 1);
+        draw_filled_rect_mut(image, rect, Rgb(color));
+    }
+    if width > 0 && span.style.strikethrough {
+        let rect = Rect::at(x, y + TEXT_SIZE as i32 / 2 + 1).of_size(width as u32, 1);
+        draw_filled_rect_mut(image, rect, Rgb(color));
+    }
+    width
+}
+
+/// 图标的透明部分和背景混合
+fn draw_icon(image: &mut RgbImage, favicon: Option<Vec<u8>>) {
+    let icon = favicon.and_then(|data| match image::load_from_memory(&data) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            tracing::debug!("decode favicon error: {}", e);
+            None
+        }
+    });
+    let icon = match icon {
+        Some(icon) => icon
+            .resize_exact(ICON_SIZE, ICON_SIZE, FilterType::Nearest)
+            .to_rgba8(),
+        None => {
+            let rect = Rect::at(ICON, ICON).of_size(ICON_SIZE, ICON_SIZE);
+            draw_filled_rect_mut(image, rect, Rgb(ICON_PLACEHOLDER));
+            return;
+        }
+    };
+    for (x, y, pixel) in icon.enumerate_pixels() {
+        let target = image.get_pixel_mut(ICON as u32 + x, ICON as u32 + y);
+        let alpha = pixel[3] as u32;
+        for channel in 0..3 {
+            target[channel] = ((pixel[channel] as u32 * alpha
+                + target[channel] as u32 * (255 - alpha))
+                / 255) as u8;
+        }
+    }
+}
+
+/// 右上角的信号格，返回左边的位置
+fn draw_signal(image: &mut RgbImage, latency: Duration) -> i32 {
+    let bars = signal_bars(latency);
+    let left = TEXT_RIGHT - 5 * 4;
+    for bar in 0..5 {
+        let height = (bar + 1) * 3;
+        let color = if bar < bars { SIGNAL } else { DARK_GRAY };
+        let rect = Rect::at(left + bar as i32 * 4, 10 + 15 - height as i32).of_size(3, height);
+        draw_filled_rect_mut(image, rect, Rgb(color));
+    }
+    left
+}
+
+pub fn render(
+    name: &str,
+    status: &ServerStatus,
+    latency: Duration,
+    font: Option<&Font>,
+) -> image::ImageResult<Vec<u8>> {
+    let mut image = RgbImage::from_pixel(WIDTH, HEIGHT, Rgb(BACKGROUND));
+    for rect in [
+        Rect::at(0, 0).of_size(WIDTH, 1),
+        Rect::at(0, HEIGHT as i32 - 1).of_size(WIDTH, 1),
+        Rect::at(0, 0).of_size(1, HEIGHT),
+        Rect::at(WIDTH as i32 - 1, 0).of_size(1, HEIGHT),
+    ] {
+        draw_filled_rect_mut(&mut image, rect, Rgb(BORDER));
+    }
+    draw_icon(&mut image, status.favicon_png());
+
+    // 右上角从右往左：信号格、在线人数、版本
+    let mut right = draw_signal(&mut image, latency) - 6;
+    if let Some(players) = &status.players {
+        for (text, color) in [
+            (players.max.to_string(), GRAY),
+            (String::from("/"), DARK_GRAY),
+            (players.online.to_string(), GRAY),
+        ] {
+            right -= text_width(font, TEXT_SIZE, &text);
+            draw_text(&mut image, font, color, (right, 8), TEXT_SIZE, &text);
+        }
+        right -= 10;
+    }
+    let version = chat::strip_codes(&status.version.name);
+    let version = fit(font, TEXT_SIZE, &version, (right - TEXT_LEFT) / 2);
+    right -= text_width(font, TEXT_SIZE, version);
+    draw_text(&mut image, font, DARK_GRAY, (right, 8), TEXT_SIZE, version);
+
+    let name = fit(font, NAME_SIZE, name, right - 10 - TEXT_LEFT);
+    draw_text(&mut image, font, WHITE, (TEXT_LEFT, 6), NAME_SIZE, name);
+
+    for (index, line) in motd_lines(chat::parse(&status.description))
+        .iter()
+        .enumerate()
+    {
+        let y = 34 + index as i32 * 22;
+        let mut x = TEXT_LEFT;
+        for span in line {
+            x += draw_span(&mut image, font, span, (x, y));
+        }
+    }
+
+    encode_png(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mc_protocol::chat::Style;
+    use base64::{engine::general_purpose, Engine as _};
+
+    #[test]
+    fn signal_bars_test() {
+        assert_eq!(signal_bars(Duration::from_millis(20)), 5);
+        assert_eq!(signal_bars(Duration::from_millis(150)), 4);
+        assert_eq!(signal_bars(Duration::from_millis(599)), 3);
+        assert_eq!(signal_bars(Duration::from_millis(999)), 2);
+        assert_eq!(signal_bars(Duration::from_secs(3)), 1);
+    }
+
+    #[test]
+    fn motd_lines_test() {
+        let description = json::parse(
+            r#"{"text":"§6Gold §rfirst\nsecond ","extra":[{"text":"line","color":"aqua"},"\nthird"]}"#,
+        )
+        .unwrap();
+        let lines = motd_lines(chat::parse(&description));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 2);
+        assert_eq!(lines[0][0].style.color, chat::parse_color("gold"));
+        assert_eq!(lines[0][1].text, "first");
+        assert_eq!(
+            lines[1],
+            vec![
+                TextSpan {
+                    text: String::from("second "),
+                    style: Style::default(),
+                },
+                TextSpan {
+                    text: String::from("line"),
+                    style: Style {
+                        color: chat::parse_color("aqua"),
+                        ..Style::default()
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn render_test() {
+        // 左半边红色、右半边透明的 8x8 图标
+        let mut icon = image::RgbaImage::new(8, 8);
+        for (x, _, pixel) in icon.enumerate_pixels_mut() {
+            *pixel = if x < 4 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            };
+        }
+        let mut favicon: Vec<u8> = Vec::new();
+        image::DynamicImage::ImageRgba8(icon)
+            .write_to(
+                &mut std::io::Cursor::new(&mut favicon),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        let mut status = ServerStatus::parse(
+            r#"{
+                "version": {"name": "1.20.1", "protocol": 763},
+                "players": {"max": 20, "online": 3},
+                "description": "§aA Minecraft Server"
+            }"#,
+        )
+        .unwrap();
+        status.favicon = Some(format!(
+            "data:image/png;base64,{}",
+            general_purpose::STANDARD.encode(&favicon)
+        ));
+
+        let data = render("survival", &status, Duration::from_millis(400), None).unwrap();
+        let image = image::load_from_memory(&data).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(
+            image.get_pixel(ICON as u32 + 1, ICON as u32 + 1),
+            &Rgb([255, 0, 0])
+        );
+        assert_eq!(
+            image.get_pixel(ICON as u32 + ICON_SIZE - 1, ICON as u32 + 1),
+            &Rgb(BACKGROUND)
+        );
+
+        // 400 ms 是 3 格信号，最高的一格是灰色
+        let left = (TEXT_RIGHT - 20) as u32;
+        assert_eq!(image.get_pixel(left + 2 * 4, 24), &Rgb(SIGNAL));
+        assert_eq!(image.get_pixel(left + 3 * 4, 24), &Rgb(DARK_GRAY));
+
+        status.favicon = None;
+        let data = render("survival", &status, Duration::from_millis(400), None).unwrap();
+        let image = image::load_from_memory(&data).unwrap().to_rgb8();
+        assert_eq!(
+            image.get_pixel(ICON as u32, ICON as u32),
+            &Rgb(ICON_PLACEHOLDER)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use super::{draw_text, encode_png, text_width};
+use crate::history::Sample;
+use crate::time::local_time;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_antialiased_line_segment_mut, draw_filled_rect_mut};
+use imageproc::pixelops::interpolate;
+use imageproc::rect::Rect;
+use rusttype::Font;
+
+// 在线人数和延迟随时间变化的折线图，人数用左边的纵轴，延迟用右边的
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+const LEFT: i32 = 50;
+const RIGHT: i32 = 740;
+const TOP: i32 = 40;
+const BOTTOM: i32 = 360;
+const TEXT_SIZE: f32 = 14.0;
+const X_TICKS: u64 = 6;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const GRID: [u8; 3] = [225, 225, 225];
+const TEXT: [u8; 3] = [60, 60, 60];
+const PLAYERS: [u8; 3] = [40, 110, 220];
+const LATENCY: [u8; 3] = [240, 140, 30];
+const OFFLINE: [u8; 3] = [250, 215, 215];
+
+/// 纵轴的最大值和刻度间隔，间隔取 1、2、5 乘 10 的幂，最多 `ticks` 格
+pub fn axis(max: f64, ticks: u32) -> (f64, f64) {
+    if max <= 0.0 {
+        return (ticks as f64, 1.0);
+    }
+    let rough = max / ticks as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude)
+        .max(1.0);
+    ((max / step).ceil() * step, step)
+}
+
+/// 一天以内显示 `时:分`，更长的范围显示 `月-日 时:分`
+pub fn time_label(time: u64, range: u64) -> String {
+    let (month, day, hour, minute) = local_time(time);
+    if range <= 24 * 3600 {
+        format!("{:02}:{:02}", hour, minute)
+    } else {
+        format!("{:02}-{:02} {:02}:{:02}", month, day, hour, minute)
+    }
+}
+
+struct Plot {
+    since: u64,
+    until: u64,
+}
+
+impl Plot {
+    fn x(&self, time: u64) -> i32 {
+        let range = (self.until - self.since).max(1) as f64;
+        let offset = time.clamp(self.since, self.until) - self.since;
+        LEFT + ((RIGHT - LEFT) as f64 * offset as f64 / range).round() as i32
+    }
+
+    fn y(value: f64, max: f64) -> i32 {
+        BOTTOM - ((BOTTOM - TOP) as f64 * (value / max).min(1.0)).round() as i32
+    }
+}
+
+fn draw_line(image: &mut RgbImage, start: (i32, i32), end: (i32, i32), color: [u8; 3]) {
+    // 画两条相邻的线让折线粗一点
+    for offset in [0, 1] {
+        draw_antialiased_line_segment_mut(
+            image,
+            (start.0, start.1 + offset),
+            (end.0, end.1 + offset),
+            Rgb(color),
+            interpolate,
+        );
+    }
+}
+
+/// 把一串点连成折线，值为 `None` 的地方断开
+fn draw_series(
+    image: &mut RgbImage,
+    plot: &Plot,
+    samples: &[Sample],
+    value: impl Fn(&Sample) -> Option<f64>,
+    max: f64,
+    color: [u8; 3],
+) {
+    let mut last: Option<(i32, i32)> = None;
+    for sample in samples {
+        let point = value(sample).map(|value| (plot.x(sample.time), Plot::y(value, max)));
+        if let (Some(start), Some(end)) = (last, point) {
+            draw_line(image, start, end, color);
+        }
+        last = point;
+    }
+}
+
+/// `samples` 按时间排序，`since` 到 `until` 是横轴的范围
+pub fn render(
+    title: &str,
+    samples: &[Sample],
+    since: u64,
+    until: u64,
+    font: Option<&Font>,
+) -> image::ImageResult<Vec<u8>> {
+    let mut image = RgbImage::from_pixel(WIDTH, HEIGHT, Rgb(BACKGROUND));
+    let plot = Plot { since, until };
+
+    // 连不上的时间段画成红色的背景
+    for (index, sample) in samples.iter().enumerate() {
+        if sample.reachable {
+            continue;
+        }
+        let start = plot.x(sample.time);
+        let end = samples
+            .get(index + 1)
+            .map_or(start + 1, |next| plot.x(next.time));
+        let width = (end - start).max(1) as u32;
+        let rect = Rect::at(start, TOP).of_size(width, (BOTTOM - TOP) as u32);
+        draw_filled_rect_mut(&mut image, rect, Rgb(OFFLINE));
+    }
+
+    let reachable = samples.iter().filter(|sample| sample.reachable);
+    let max_players = reachable
+        .clone()
+        .map(|sample| sample.online)
+        .max()
+        .unwrap_or(0);
+    let max_latency = reachable
+        .filter_map(|sample| sample.latency)
+        .max()
+        .map_or(0, |latency| latency.as_millis());
+    let (players_max, players_step) = axis(max_players as f64, 5);
+    let (latency_max, latency_step) = axis(max_latency as f64, 5);
+
+    // 横向的网格线和两边的刻度
+    let ticks = (players_max / players_step).round() as u32;
+    for tick in 0..=ticks {
+        let y = BOTTOM - (BOTTOM - TOP) * tick as i32 / ticks as i32;
+        draw_filled_rect_mut(
+            &mut image,
+            Rect::at(LEFT, y).of_size((RIGHT - LEFT) as u32, 1),
+            Rgb(GRID),
+        );
+        let label = format!("{}", (players_step * tick as f64) as i64);
+        let width = text_width(font, TEXT_SIZE, &label);
+        draw_text(
+            &mut image,
+            font,
+            PLAYERS,
+            (LEFT - 6 - width, y - 8),
+            TEXT_SIZE,
+            &label,
+        );
+    }
+    let ticks = (latency_max / latency_step).round() as u32;
+    for tick in 0..=ticks {
+        let y = Plot::y(latency_step * tick as f64, latency_max);
+        let label = format!("{}", (latency_step * tick as f64) as i64);
+        draw_text(
+            &mut image,
+            font,
+            LATENCY,
+            (RIGHT + 6, y - 8),
+            TEXT_SIZE,
+            &label,
+        );
+    }
+
+    // 纵向的网格线和时间
+    let range = until.saturating_sub(since);
+    for tick in 0..=X_TICKS {
+        let time = since + range * tick / X_TICKS;
+        let x = plot.x(time);
+        draw_filled_rect_mut(
+            &mut image,
+            Rect::at(x, TOP).of_size(1, (BOTTOM - TOP) as u32),
+            Rgb(GRID),
+        );
+        let label = time_label(time, range);
+        let width = text_width(font, TEXT_SIZE, &label);
+        draw_text(
+            &mut image,
+            font,
+            TEXT,
+            (x - width / 2, BOTTOM + 8),
+            TEXT_SIZE,
+            &label,
+        );
+    }
+
+    draw_series(
+        &mut image,
+        &plot,
+        samples,
+        |sample| sample.latency.map(|latency| latency.as_millis() as f64),
+        latency_max,
+        LATENCY,
+    );
+    draw_series(
+        &mut image,
+        &plot,
+        samples,
+        |sample| Some(sample.online as f64).filter(|_| sample.reachable),
+        players_max,
+        PLAYERS,
+    );
+
+    // 标题和图例
+    draw_text(&mut image, font, TEXT, (LEFT, 12), 16.0, title);
+    let mut x = RIGHT;
+    for (label, color) in [("latency (ms)", LATENCY), ("players", PLAYERS)] {
+        x -= text_width(font, TEXT_SIZE, label);
+        draw_text(&mut image, font, TEXT, (x, 14), TEXT_SIZE, label);
+        x -= 18;
+        draw_filled_rect_mut(&mut image, Rect::at(x, 18).of_size(12, 10), Rgb(color));
+        x -= 16;
+    }
+
+    encode_png(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn axis_test() {
+        assert_eq!(axis(0.0, 5), (5.0, 1.0));
+        assert_eq!(axis(3.0, 5), (3.0, 1.0));
+        assert_eq!(axis(20.0, 5), (20.0, 5.0));
+        assert_eq!(axis(37.0, 5), (40.0, 10.0));
+        assert_eq!(axis(180.0, 5), (200.0, 50.0));
+    }
+
+    #[test]
+    fn time_label_test() {
+        // 2023-06-01 21:30 北京时间
+        let time = 1685548800 + 21 * 3600 + 1800;
+        assert_eq!(time_label(time, 24 * 3600), "21:30");
+        assert_eq!(time_label(time, 7 * 24 * 3600), "06-01 21:30");
+    }
+
+    #[test]
+    fn render_test() {
+        let samples: Vec<Sample> = (0..100)
+            .map(|i| match i {
+                40..=45 => Sample::unreachable(i * 60),
+                _ => Sample {
+                    time: i * 60,
+                    reachable: true,
+                    online: (i % 10) as i64,
+                    max: 20,
+                    latency: Some(Duration::from_millis(20 + i)),
+                },
+            })
+            .collect();
+        let data = render("survival", &samples, 0, 100 * 60, None).unwrap();
+        let image = image::load_from_memory(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (WIDTH, HEIGHT));
+
+        let image = image.to_rgb8();
+        assert_eq!(
+            image.get_pixel(plot_x(42), BOTTOM as u32 - 1),
+            &Rgb(OFFLINE)
+        );
+        assert_eq!(image.get_pixel(WIDTH - 1, HEIGHT - 1), &Rgb(BACKGROUND));
+
+        assert!(render("empty", &[], 0, 3600, None).is_ok());
+    }
+
+    fn plot_x(minute: u64) -> u32 {
+        Plot {
+            since: 0,
+            until: 100 * 60,
+        }
+        .x(minute * 60 + 30) as u32
+    }
+}
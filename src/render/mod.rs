@@ -0,0 +1,99 @@
+use crate::config;
+use image::{ImageOutputFormat, RgbImage};
+use imageproc::drawing::draw_text_mut;
+use rusttype::{point, Font, Scale};
+use std::io::Cursor;
+use std::sync::OnceLock;
+
+// 在本地把图表、状态卡片等画成 PNG 发到群里
+
+pub mod card;
+pub mod chart;
+
+/// 没有配置字体时依次尝试，优先用带中文的字体
+const FONT_CANDIDATES: [&str; 6] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+];
+
+pub fn load_font(path: &str) -> Option<Font<'static>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::debug!("read font {} error: {}", path, e);
+            return None;
+        }
+    };
+    let font = Font::try_from_vec(data);
+    if font.is_none() {
+        tracing::warn!("{} is not a valid font", path);
+    }
+    font
+}
+
+static FONT: OnceLock<Option<Font<'static>>> = OnceLock::new();
+
+/// 第一次调用时加载，找不到字体时返回 `None`，这时图片里不画文字
+pub fn font() -> Option<&'static Font<'static>> {
+    FONT.get_or_init(|| {
+        let font = match &config::get().font {
+            Some(path) => load_font(path),
+            None => FONT_CANDIDATES.iter().find_map(|path| load_font(path)),
+        };
+        if font.is_none() {
+            tracing::warn!("no font found, images will be rendered without text");
+        }
+        font
+    })
+    .as_ref()
+}
+
+pub fn encode_png(image: RgbImage) -> image::ImageResult<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    image::DynamicImage::ImageRgb8(image)
+        .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)?;
+    Ok(data)
+}
+
+/// 没有字体时什么都不画，返回文字的宽度
+pub(crate) fn draw_text(
+    image: &mut RgbImage,
+    font: Option<&Font>,
+    color: [u8; 3],
+    (x, y): (i32, i32),
+    size: f32,
+    text: &str,
+) -> i32 {
+    let font = match font {
+        Some(font) => font,
+        None => return 0,
+    };
+    draw_text_mut(
+        image,
+        image::Rgb(color),
+        x,
+        y,
+        Scale::uniform(size),
+        font,
+        text,
+    );
+    text_width(Some(font), size, text)
+}
+
+/// 文字占的宽度，包括末尾的空格，用来对齐和接着画下一段
+pub(crate) fn text_width(font: Option<&Font>, size: f32, text: &str) -> i32 {
+    let font = match font {
+        Some(font) => font,
+        None => return 0,
+    };
+    font.layout(text, Scale::uniform(size), point(0.0, 0.0))
+        .last()
+        .map_or(0, |glyph| {
+            let advance = glyph.unpositioned().h_metrics().advance_width;
+            (glyph.position().x + advance).ceil() as i32
+        })
+}
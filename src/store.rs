@@ -0,0 +1,74 @@
+use json::JsonValue;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// 持久化到工作目录下 json 文件的数据，例如群绑定的服务器
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Json(json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "io error: {}", e),
+            StoreError::Json(e) => write!(f, "parse json error: {}", e),
+            StoreError::Invalid(msg) => write!(f, "invalid data: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<json::Error> for StoreError {
+    fn from(e: json::Error) -> Self {
+        StoreError::Json(e)
+    }
+}
+
+/// 文件不存在时返回 `None`
+pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Option<JsonValue>, StoreError> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(Some(json::parse(&data)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 先写到临时文件再重命名，避免写到一半退出时把原来的文件弄坏
+pub fn save_json<P: AsRef<Path>>(path: P, value: &JsonValue) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, value.pretty(2))?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_load_test() {
+        let path = std::env::temp_dir().join(format!("qq-bot-store-{}.json", std::process::id()));
+        assert!(load_json(&path).unwrap().is_none());
+
+        let value = json::object! {"a": 1, "b": ["c"]};
+        save_json(&path, &value).unwrap();
+        assert_eq!(load_json(&path).unwrap(), Some(value));
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(load_json(&path), Err(StoreError::Json(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 时间戳和北京时间的转换，统计、图表和各种消息里显示的时间都用这里的函数
+
+/// 统一按北京时间显示
+pub const UTC_OFFSET: i64 = 8 * 3600;
+
+/// 当前的 unix 时间戳，单位秒
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// unix 时间戳转成北京时间的 `(年, 月, 日, 时, 分)`
+fn civil_time(time: u64) -> (i64, u32, u32, u32, u32) {
+    let secs = time as i64 + UTC_OFFSET;
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400);
+
+    // 参考 http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month as u32,
+        day as u32,
+        (secs_of_day / 3600) as u32,
+        (secs_of_day % 3600 / 60) as u32,
+    )
+}
+
+/// unix 时间戳转成北京时间的 `(月, 日, 时, 分)`
+pub fn local_time(time: u64) -> (u32, u32, u32, u32) {
+    let (_, month, day, hour, minute) = civil_time(time);
+    (month, day, hour, minute)
+}
+
+/// `2023-06-01`
+pub fn format_date(time: u64) -> String {
+    let (year, month, day, _, _) = civil_time(time);
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+/// `06-01 21:30`
+pub fn format_time(time: u64) -> String {
+    let (month, day, hour, minute) = local_time(time);
+    format!("{:02}-{:02} {:02}:{:02}", month, day, hour, minute)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_test() {
+        assert_eq!(local_time(1685548800 + 21 * 3600 + 30 * 60), (6, 1, 21, 30));
+        assert_eq!(format_time(1685548800), "06-01 00:00");
+        assert_eq!(format_time(1704038400 + 3600 + 60), "01-01 01:01");
+        assert_eq!(format_date(1685548800), "2023-06-01");
+        assert_eq!(format_date(1704038400 - 1), "2023-12-31");
+        assert_eq!(format_date(1709136000), "2024-02-29");
+    }
+}
@@ -0,0 +1,54 @@
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+/// 网络请求的超时设置
+/// - connect: 建立 TCP 连接
+/// - read: 每次等待对方回复
+/// - total: 整个命令，包括 DNS 查询
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub read: Duration,
+    pub total: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect: Duration::from_secs(5),
+            read: Duration::from_secs(5),
+            total: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 超时的时候返回 `ErrorKind::TimedOut`，各模块的错误类型都可以直接用 `?` 转换
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> io::Result<F::Output> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timeout_test() {
+        tokio_test::block_on(async {
+            assert_eq!(
+                timeout(Duration::from_secs(1), async { 1 }).await.unwrap(),
+                1
+            );
+
+            let e = timeout(
+                Duration::from_millis(10),
+                tokio::time::sleep(Duration::from_secs(10)),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use crate::mc_protocol::query;
+use crate::mc_protocol::resolve::Resolver;
+use crate::mc_protocol::status::{self, ServerStatus};
+use crate::timeout::Timeouts;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+// 定时 ping 服务器，比较前后两次的结果得到玩家进出和离线/恢复的事件
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchEvent {
+    Joined(String),
+    Left(String),
+    Offline,
+    Online { downtime: Duration },
+}
+
+/// 一次成功的 ping 的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub online: i64,
+    pub max: i64,
+    pub latency: Duration,
+    /// 拿不到完整的玩家列表时为 `None`，这时不比较玩家
+    pub players: Option<BTreeSet<String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ServerWatch {
+    /// 第一次 ping 之前为 `None`
+    online: Option<bool>,
+    players: Option<BTreeSet<String>>,
+    failures: u32,
+    first_failure: Option<Instant>,
+}
+
+impl ServerWatch {
+    /// 连续失败 `offline_threshold` 次才算离线，第一次成功的结果只作为基准不产生事件
+    pub fn update(
+        &mut self,
+        snapshot: Option<Snapshot>,
+        now: Instant,
+        offline_threshold: u32,
+    ) -> Vec<WatchEvent> {
+        let mut events: Vec<WatchEvent> = Vec::new();
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                self.failures += 1;
+                self.first_failure.get_or_insert(now);
+                // 启动时就连不上的服务器不播报，等它第一次成功后再开始
+                if self.online == Some(true) && self.failures >= offline_threshold {
+                    events.push(WatchEvent::Offline);
+                    self.online = Some(false);
+                    self.players = None;
+                }
+                return events;
+            }
+        };
+
+        match self.online {
+            Some(false) => {
+                let downtime = self
+                    .first_failure
+                    .map(|first_failure| now.duration_since(first_failure))
+                    .unwrap_or_default();
+                events.push(WatchEvent::Online { downtime });
+            }
+            Some(true) => {
+                if let (Some(old), Some(new)) = (&self.players, &snapshot.players) {
+                    for name in new.difference(old) {
+                        events.push(WatchEvent::Joined(name.clone()));
+                    }
+                    for name in old.difference(new) {
+                        events.push(WatchEvent::Left(name.clone()));
+                    }
+                }
+            }
+            None => {}
+        }
+
+        self.online = Some(true);
+        self.failures = 0;
+        self.first_failure = None;
+        // 这次拿不到完整列表时保留上次的，避免下次把所有人都当成新加入
+        if snapshot.players.is_some() {
+            self.players = snapshot.players;
+        }
+        events
+    }
+}
+
+/// 状态 ping 的结果，sample 不完整或者服务器隐藏了玩家信息时 `players` 为 `None`
+fn from_status(status: ServerStatus, latency: Duration) -> Snapshot {
+    match status.players {
+        Some(players) if players.sample.len() as i64 == players.online => Snapshot {
+            online: players.online,
+            max: players.max,
+            latency,
+            players: Some(
+                players
+                    .sample
+                    .into_iter()
+                    .map(|sample| sample.name)
+                    .collect(),
+            ),
+        },
+        Some(players) => Snapshot {
+            online: players.online,
+            max: players.max,
+            latency,
+            players: None,
+        },
+        None => Snapshot {
+            online: 0,
+            max: 0,
+            latency,
+            players: None,
+        },
+    }
+}
+
+/// 先用状态 ping，sample 不完整时再尝试 Query 拿完整的玩家列表
+/// Query 使用状态 ping 解析出的地址，这样只有 SRV 记录的服务器也能查到
+pub async fn poll(resolver: &dyn Resolver, address: &str, timeouts: &Timeouts) -> Option<Snapshot> {
+    let response = match status::ping(resolver, address, timeouts).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::debug!("watch ping {} error: {}", address, e);
+            return None;
+        }
+    };
+    let mut snapshot = from_status(response.status, response.latency);
+    if snapshot.players.is_some() {
+        return Some(snapshot);
+    }
+
+    snapshot.players = match query::query_resolved(response.address, timeouts).await {
+        Ok(response) => Some(response.stat.players.into_iter().collect()),
+        Err(e) => {
+            tracing::debug!("watch query {} error: {}", address, e);
+            None
+        }
+    };
+    Some(snapshot)
+}
+
+/// 把时长格式化成 `1 小时 5 分钟` 这样的文本
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => String::from("不到 1 分钟"),
+        (0, minutes) => format!("{} 分钟", minutes),
+        (hours, 0) => format!("{} 小时", hours),
+        (hours, minutes) => format!("{} 小时 {} 分钟", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot(players: &[&str]) -> Option<Snapshot> {
+        Some(Snapshot {
+            online: players.len() as i64,
+            max: 20,
+            latency: Duration::from_millis(30),
+            players: Some(players.iter().map(|name| name.to_string()).collect()),
+        })
+    }
+
+    #[test]
+    fn players_test() {
+        let now = Instant::now();
+        let mut watch = ServerWatch::default();
+        assert!(watch.update(snapshot(&["Steve"]), now, 3).is_empty());
+        assert_eq!(
+            watch.update(snapshot(&["Alex", "Steve"]), now, 3),
+            vec![WatchEvent::Joined(String::from("Alex"))]
+        );
+        assert_eq!(
+            watch.update(snapshot(&["Alex"]), now, 3),
+            vec![WatchEvent::Left(String::from("Steve"))]
+        );
+
+        // 列表不完整的时候不比较，也不覆盖之前的列表
+        let incomplete = Some(Snapshot {
+            online: 20,
+            max: 20,
+            latency: Duration::from_millis(30),
+            players: None,
+        });
+        assert!(watch.update(incomplete, now, 3).is_empty());
+        assert_eq!(
+            watch.update(snapshot(&[]), now, 3),
+            vec![WatchEvent::Left(String::from("Alex"))]
+        );
+    }
+
+    #[test]
+    fn outage_test() {
+        let start = Instant::now();
+        let mut watch = ServerWatch::default();
+        watch.update(snapshot(&["Steve"]), start, 3);
+
+        // 偶尔失败一两次不算离线
+        assert!(watch.update(None, start, 3).is_empty());
+        assert!(watch.update(None, start, 3).is_empty());
+        assert!(watch.update(snapshot(&["Steve"]), start, 3).is_empty());
+
+        for _ in 0..2 {
+            assert!(watch.update(None, start, 3).is_empty());
+        }
+        assert_eq!(watch.update(None, start, 3), vec![WatchEvent::Offline]);
+        assert!(watch.update(None, start, 3).is_empty());
+
+        let later = start + Duration::from_secs(12 * 60);
+        assert_eq!(
+            watch.update(snapshot(&["Alex"]), later, 3),
+            vec![WatchEvent::Online {
+                downtime: Duration::from_secs(12 * 60)
+            }]
+        );
+        // 恢复后重新以当前列表为基准
+        assert!(watch.update(snapshot(&["Alex"]), later, 3).is_empty());
+    }
+
+    #[test]
+    fn offline_at_start_test() {
+        let now = Instant::now();
+        let mut watch = ServerWatch::default();
+        for _ in 0..3 {
+            assert!(watch.update(None, now, 3).is_empty());
+        }
+        assert!(watch.update(snapshot(&["Steve"]), now, 3).is_empty());
+        assert_eq!(
+            watch.update(snapshot(&[]), now, 3),
+            vec![WatchEvent::Left(String::from("Steve"))]
+        );
+    }
+
+    #[test]
+    fn hidden_players_test() {
+        let status = ServerStatus::parse(
+            r#"{"version": {"name": "1.20.1", "protocol": 763}, "description": "A Minecraft Server"}"#,
+        )
+        .unwrap();
+        let hidden = from_status(status, Duration::from_millis(30));
+        assert_eq!(
+            hidden,
+            Snapshot {
+                online: 0,
+                max: 0,
+                latency: Duration::from_millis(30),
+                players: None,
+            }
+        );
+
+        // 能 ping 通就不算离线，也不覆盖之前的列表
+        let now = Instant::now();
+        let mut watch = ServerWatch::default();
+        watch.update(snapshot(&["Steve"]), now, 1);
+        assert!(watch.update(Some(hidden), now, 1).is_empty());
+        assert_eq!(
+            watch.update(snapshot(&[]), now, 1),
+            vec![WatchEvent::Left(String::from("Steve"))]
+        );
+    }
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!(format_duration(Duration::from_secs(30)), "不到 1 分钟");
+        assert_eq!(format_duration(Duration::from_secs(12 * 60)), "12 分钟");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1 小时");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1 小时 5 分钟");
+    }
+}
@@ -0,0 +1,378 @@
+use crate::mc_protocol::is_valid_player_name;
+use crate::store::{load_json, save_json, StoreError};
+use json::JsonValue;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// 白名单申请和 QQ 号与游戏账号的绑定，保存在 whitelist.json 中
+// {"next_id": 3, "requests": [{"id": 2, "group": 1, "uin": 10001, "name": "Steve", "time": 0}],
+//  "accounts": {"群号": {"QQ 号": "Alex"}}}
+
+pub const WHITELIST_PATH: &str = "whitelist.json";
+
+/// 等待管理员处理的申请
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub id: u32,
+    pub group: i64,
+    pub uin: i64,
+    pub name: String,
+    /// unix 时间戳，单位秒
+    pub time: u64,
+}
+
+#[derive(Debug)]
+pub enum ApplyError {
+    InvalidName,
+    /// 已经有一个没处理的申请
+    Pending(u32),
+    /// 已经绑定了账号
+    Bound(String),
+    /// 这个名字已经被别人申请或绑定了
+    NameTaken,
+    Store(StoreError),
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::InvalidName => write!(f, "invalid player name"),
+            ApplyError::Pending(id) => write!(f, "request #{} is pending", id),
+            ApplyError::Bound(name) => write!(f, "already bound to {}", name),
+            ApplyError::NameTaken => write!(f, "name is taken"),
+            ApplyError::Store(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+impl From<StoreError> for ApplyError {
+    fn from(e: StoreError) -> Self {
+        ApplyError::Store(e)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Whitelist {
+    path: Option<PathBuf>,
+    next_id: u32,
+    requests: Vec<Request>,
+    /// 群号 -> QQ 号 -> 游戏名
+    accounts: BTreeMap<i64, BTreeMap<i64, String>>,
+}
+
+fn invalid(msg: String) -> StoreError {
+    StoreError::Invalid(msg)
+}
+
+impl Whitelist {
+    pub fn from_json(value: &JsonValue) -> Result<Whitelist, StoreError> {
+        let mut whitelist = Whitelist {
+            next_id: value["next_id"].as_u32().unwrap_or(1),
+            ..Whitelist::default()
+        };
+        for request in value["requests"].members() {
+            let field = |key: &str| {
+                request[key]
+                    .as_i64()
+                    .ok_or_else(|| invalid(format!("request.{} is missing", key)))
+            };
+            whitelist.requests.push(Request {
+                id: field("id")? as u32,
+                group: field("group")?,
+                uin: field("uin")?,
+                name: request["name"]
+                    .as_str()
+                    .ok_or_else(|| invalid(String::from("request.name is missing")))?
+                    .to_string(),
+                time: request["time"].as_u64().unwrap_or_default(),
+            });
+        }
+        for (group, accounts) in value["accounts"].entries() {
+            let group: i64 = group
+                .parse()
+                .map_err(|_| invalid(format!("invalid group {:?}", group)))?;
+            let group_accounts = whitelist.accounts.entry(group).or_default();
+            for (uin, name) in accounts.entries() {
+                let uin: i64 = uin
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid uin {:?}", uin)))?;
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| invalid(format!("name of {} is not a string", uin)))?;
+                group_accounts.insert(uin, name.to_string());
+            }
+        }
+        Ok(whitelist)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut requests = JsonValue::new_array();
+        for request in &self.requests {
+            requests
+                .push(json::object! {
+                    "id": request.id,
+                    "group": request.group,
+                    "uin": request.uin,
+                    "name": request.name.as_str(),
+                    "time": request.time,
+                })
+                .unwrap();
+        }
+        let mut accounts = JsonValue::new_object();
+        for (group, group_accounts) in &self.accounts {
+            let mut value = JsonValue::new_object();
+            for (uin, name) in group_accounts {
+                value[uin.to_string()] = name.as_str().into();
+            }
+            accounts[group.to_string()] = value;
+        }
+        json::object! {
+            "next_id": self.next_id,
+            "requests": requests,
+            "accounts": accounts,
+        }
+    }
+
+    /// 文件不存在时返回空的白名单，之后的修改会保存到 `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Whitelist, StoreError> {
+        let mut whitelist = match load_json(&path)? {
+            Some(value) => Whitelist::from_json(&value)?,
+            None => Whitelist::default(),
+        };
+        whitelist.path = Some(path.as_ref().to_path_buf());
+        Ok(whitelist)
+    }
+
+    fn save(&self) -> Result<(), StoreError> {
+        match &self.path {
+            Some(path) => Ok(save_json(path, &self.to_json())?),
+            None => Ok(()),
+        }
+    }
+
+    /// 提交申请，返回申请的编号
+    pub fn apply(
+        &mut self,
+        group: i64,
+        uin: i64,
+        name: &str,
+        time: u64,
+    ) -> Result<u32, ApplyError> {
+        if !is_valid_player_name(name) {
+            return Err(ApplyError::InvalidName);
+        }
+        if let Some(request) = self
+            .requests
+            .iter()
+            .find(|request| request.group == group && request.uin == uin)
+        {
+            return Err(ApplyError::Pending(request.id));
+        }
+        if let Some(bound) = self.account(group, uin) {
+            return Err(ApplyError::Bound(bound.to_string()));
+        }
+        // 游戏名不区分大小写
+        let taken = self
+            .requests
+            .iter()
+            .filter(|request| request.group == group)
+            .map(|request| &request.name)
+            .chain(
+                self.accounts
+                    .get(&group)
+                    .into_iter()
+                    .flat_map(|accounts| accounts.values()),
+            )
+            .any(|taken| taken.eq_ignore_ascii_case(name));
+        if taken {
+            return Err(ApplyError::NameTaken);
+        }
+
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        self.requests.push(Request {
+            id,
+            group,
+            uin,
+            name: name.to_string(),
+            time,
+        });
+        self.save()?;
+        Ok(id)
+    }
+
+    pub fn pending(&self, group: i64) -> Vec<&Request> {
+        self.requests
+            .iter()
+            .filter(|request| request.group == group)
+            .collect()
+    }
+
+    pub fn request(&self, group: i64, id: u32) -> Option<&Request> {
+        self.requests
+            .iter()
+            .find(|request| request.group == group && request.id == id)
+    }
+
+    /// 通过申请并记录绑定，应该在服务器上加入白名单成功之后调用
+    pub fn approve(&mut self, group: i64, id: u32) -> Result<Option<Request>, StoreError> {
+        let request = match self.remove(group, id) {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+        self.accounts
+            .entry(group)
+            .or_default()
+            .insert(request.uin, request.name.clone());
+        self.save()?;
+        Ok(Some(request))
+    }
+
+    pub fn deny(&mut self, group: i64, id: u32) -> Result<Option<Request>, StoreError> {
+        let request = self.remove(group, id);
+        if request.is_some() {
+            self.save()?;
+        }
+        Ok(request)
+    }
+
+    fn remove(&mut self, group: i64, id: u32) -> Option<Request> {
+        let index = self
+            .requests
+            .iter()
+            .position(|request| request.group == group && request.id == id)?;
+        Some(self.requests.remove(index))
+    }
+
+    /// 删除绑定，返回原来绑定的游戏名，应该在服务器上移出白名单成功之后调用
+    pub fn unlink(&mut self, group: i64, uin: i64) -> Result<Option<String>, StoreError> {
+        let name = match self.accounts.get_mut(&group) {
+            Some(accounts) => accounts.remove(&uin),
+            None => None,
+        };
+        if name.is_none() {
+            return Ok(None);
+        }
+        if self
+            .accounts
+            .get(&group)
+            .is_some_and(|accounts| accounts.is_empty())
+        {
+            self.accounts.remove(&group);
+        }
+        self.save()?;
+        Ok(name)
+    }
+
+    /// 绑定了这个游戏名的 QQ 号，不区分大小写
+    pub fn owner(&self, group: i64, name: &str) -> Option<i64> {
+        self.accounts
+            .get(&group)?
+            .iter()
+            .find(|(_, bound)| bound.eq_ignore_ascii_case(name))
+            .map(|(uin, _)| *uin)
+    }
+
+    /// QQ 号在这个群里绑定的游戏名
+    pub fn account(&self, group: i64, uin: i64) -> Option<&str> {
+        self.accounts
+            .get(&group)?
+            .get(&uin)
+            .map(|name| name.as_str())
+    }
+}
+
+static WHITELIST: OnceLock<Mutex<Whitelist>> = OnceLock::new();
+
+/// 第一次使用时从 `WHITELIST_PATH` 读取
+///
+/// 读取失败时记录日志并使用空的白名单，这时不再保存，避免覆盖掉原来的文件
+pub fn global() -> &'static Mutex<Whitelist> {
+    WHITELIST.get_or_init(|| {
+        let whitelist = Whitelist::load(WHITELIST_PATH).unwrap_or_else(|e| {
+            tracing::error!(
+                "load {} error: {}, changes will not be saved until it is fixed",
+                WHITELIST_PATH,
+                e
+            );
+            Whitelist::default()
+        });
+        Mutex::new(whitelist)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply_test() {
+        let mut whitelist = Whitelist::default();
+        assert_eq!(whitelist.apply(1, 10001, "Steve", 0).unwrap(), 1);
+        assert_eq!(whitelist.apply(1, 10002, "Alex", 0).unwrap(), 2);
+        assert!(matches!(
+            whitelist.apply(1, 10001, "Steve2", 0),
+            Err(ApplyError::Pending(1))
+        ));
+        assert!(matches!(
+            whitelist.apply(1, 10003, "steve", 0),
+            Err(ApplyError::NameTaken)
+        ));
+        assert!(matches!(
+            whitelist.apply(1, 10003, "No Spaces", 0),
+            Err(ApplyError::InvalidName)
+        ));
+        // 不同的群互不影响
+        assert_eq!(whitelist.apply(2, 10001, "Steve", 0).unwrap(), 3);
+        assert_eq!(whitelist.pending(1).len(), 2);
+
+        let request = whitelist.approve(1, 1).unwrap().unwrap();
+        assert_eq!(request.name, "Steve");
+        assert_eq!(whitelist.account(1, 10001), Some("Steve"));
+        assert!(matches!(
+            whitelist.apply(1, 10001, "Other", 0),
+            Err(ApplyError::Bound(_))
+        ));
+        assert!(matches!(
+            whitelist.apply(1, 10003, "STEVE", 0),
+            Err(ApplyError::NameTaken)
+        ));
+
+        assert_eq!(whitelist.deny(1, 2).unwrap().unwrap().name, "Alex");
+        assert_eq!(whitelist.deny(1, 2).unwrap(), None);
+        assert_eq!(whitelist.approve(1, 3).unwrap(), None);
+        assert_eq!(whitelist.account(1, 10002), None);
+        assert!(whitelist.request(2, 3).is_some());
+
+        assert_eq!(whitelist.owner(1, "STEVE"), Some(10001));
+        assert_eq!(
+            whitelist.unlink(1, 10001).unwrap().as_deref(),
+            Some("Steve")
+        );
+        assert_eq!(whitelist.unlink(1, 10001).unwrap(), None);
+        assert_eq!(whitelist.owner(1, "Steve"), None);
+        // 解绑后可以重新申请
+        assert!(whitelist.apply(1, 10003, "Steve", 0).is_ok());
+    }
+
+    #[test]
+    fn persist_test() {
+        let path =
+            std::env::temp_dir().join(format!("qq-bot-whitelist-{}.json", std::process::id()));
+        let mut whitelist = Whitelist::load(&path).unwrap();
+        whitelist.apply(123456, 10001, "Steve", 100).unwrap();
+        whitelist.apply(123456, 10002, "Alex", 200).unwrap();
+        whitelist.approve(123456, 1).unwrap();
+
+        let mut loaded = Whitelist::load(&path).unwrap();
+        assert_eq!(loaded.account(123456, 10001), Some("Steve"));
+        assert_eq!(loaded.pending(123456), whitelist.pending(123456));
+        // 编号不会重复使用
+        assert_eq!(loaded.apply(123456, 10003, "Herobrine", 300).unwrap(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}